@@ -0,0 +1,300 @@
+// File: clips.rs
+//
+// Animation clips are read from `.anim.ron` files, one per sprite sheet, so
+// frames and timings can be changed without touching code. Edits are picked
+// up while the game runs.
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::{BoxedFuture, HashMap};
+use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
+
+use crate::animations::components::AnimationState;
+use crate::Direction;
+
+/// Animation clip files, loaded during `GameStates::AssetLoading`.
+#[derive(AssetCollection, Resource)]
+pub struct AnimationAssets {
+    #[asset(path = "animations/player.anim.ron")]
+    pub player: Handle<AnimationClips>,
+}
+
+/// What happens when a clip reaches its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum PlaybackMode {
+    /// Start over from the first frame.
+    #[default]
+    Loop,
+    /// Play backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stay on the last frame.
+    Once,
+}
+
+/// A single animation, as a list of sprite sheet indices.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnimationClip {
+    pub frames: Vec<usize>,
+    /// Seconds each frame is shown, either one per frame or a single value
+    /// for all of them.
+    pub durations: Vec<f32>,
+    #[serde(default)]
+    pub mode: PlaybackMode,
+    /// Names of the `AnimationEvent`s to send when a frame is shown, keyed by
+    /// the frame's position in `frames`.
+    #[serde(default)]
+    pub events: HashMap<usize, Vec<String>>,
+}
+
+impl AnimationClip {
+    /// Seconds frame number `frame` of the clip is shown.
+    pub fn duration(&self, frame: usize) -> f32 {
+        self.durations
+            .get(frame)
+            .or(self.durations.first())
+            .copied()
+            .unwrap_or(0.1)
+    }
+
+    /// The frame after `frame`, and whether the clip plays forwards from
+    /// there. Only `PingPong` clips ever play backwards.
+    pub fn next(&self, frame: usize, forwards: bool) -> (usize, bool) {
+        let last = self.frames.len().saturating_sub(1);
+        match self.mode {
+            PlaybackMode::Loop => (if frame >= last { 0 } else { frame + 1 }, true),
+            PlaybackMode::Once => ((frame + 1).min(last), true),
+            PlaybackMode::PingPong if last == 0 => (0, true),
+            PlaybackMode::PingPong => match forwards {
+                true if frame >= last => (last - 1, false),
+                true => (frame + 1, true),
+                false if frame == 0 => (1, true),
+                false => (frame - 1, false),
+            },
+        }
+    }
+
+    /// Names of the events on frame number `frame`.
+    pub fn events_at(&self, frame: usize) -> &[String] {
+        self.events.get(&frame).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether the clip has played through once when `frame` is done.
+    pub fn ends_cycle(&self, frame: usize, forwards: bool) -> bool {
+        let last = self.frames.len().saturating_sub(1);
+        match self.mode {
+            PlaybackMode::PingPong => last == 0 || (frame == 0 && !forwards),
+            PlaybackMode::Loop | PlaybackMode::Once => frame >= last,
+        }
+    }
+
+    /// Checks that the clip can be played. Its frames are only checked
+    /// against the sprite sheet when `atlas_len` is known.
+    pub fn check(&self, atlas_len: Option<usize>) -> Result<(), String> {
+        if self.frames.is_empty() {
+            return Err("has no frames".to_string());
+        }
+        if self.durations.len() != 1 && self.durations.len() != self.frames.len() {
+            return Err(format!(
+                "has {} frames but {} durations, give one or one per frame",
+                self.frames.len(),
+                self.durations.len()
+            ));
+        }
+        if let Some(duration) = self.durations.iter().find(|duration| !duration.is_finite() || **duration <= 0.) {
+            return Err(format!("has a duration of {}, durations must be more than 0 seconds", duration));
+        }
+        if let Some(frame) = self.events.keys().find(|frame| **frame >= self.frames.len()) {
+            return Err(format!(
+                "has events on frame {}, but only {} frames",
+                frame,
+                self.frames.len()
+            ));
+        }
+        if let Some((frame, len)) = atlas_len
+            .and_then(|len| self.frames.iter().find(|frame| **frame >= len).map(|frame| (frame, len)))
+        {
+            return Err(format!("shows sprite {}, but the sprite sheet only has {}", frame, len));
+        }
+        Ok(())
+    }
+}
+
+/// The clips of one sprite sheet, keyed by state and direction, e.g.
+/// `WalkSouth`.
+#[derive(Debug, Clone, Deserialize, TypeUuid, TypePath)]
+#[uuid = "6b0e1d9a-3c57-4f0e-9a51-0d3f8b7c2e14"]
+pub struct AnimationClips {
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+impl AnimationClips {
+    /// The clip for `state` facing `direction`, and whether it has to be
+    /// flipped horizontally. Sheets without West clips use the East ones
+    /// flipped, and states without clips of their own fall back to `Idle`.
+    pub fn clip(&self, state: AnimationState, direction: Direction) -> Option<(&AnimationClip, bool)> {
+        [state, AnimationState::Idle]
+            .into_iter()
+            .find_map(|state| self.facing_clip(state, direction))
+    }
+
+    /// Checks every clip, see `AnimationClip::check`.
+    pub fn check(&self, atlas_len: Option<usize>) -> Result<(), String> {
+        for (name, clip) in self.clips.iter() {
+            clip.check(atlas_len).map_err(|e| format!("clip {} {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    fn facing_clip(&self, state: AnimationState, direction: Direction) -> Option<(&AnimationClip, bool)> {
+        let named = |direction: Direction| self.clips.get(&format!("{:?}{:?}", state, direction));
+        match direction {
+            Direction::West => named(Direction::West)
+                .map(|clip| (clip, false))
+                .or_else(|| named(Direction::East).map(|clip| (clip, true))),
+            direction => named(direction).map(|clip| (clip, false)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationClipsLoader;
+
+impl AssetLoader for AnimationClipsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let clips: AnimationClips = ron::de::from_bytes(bytes)?;
+            // The sprite sheet is checked against once it is in use.
+            clips.check(None).map_err(bevy::asset::Error::msg)?;
+            load_context.set_default_asset(LoadedAsset::new(clips));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playback_modes_step_through_frames() {
+        let clip = |mode| AnimationClip {
+            frames: vec![10, 11, 12],
+            durations: vec![0.1],
+            mode,
+            events: HashMap::new(),
+        };
+        let play = |clip: AnimationClip| {
+            let mut state = (0, true);
+            (0..5)
+                .map(|_| {
+                    state = clip.next(state.0, state.1);
+                    clip.frames[state.0]
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(play(clip(PlaybackMode::Loop)), vec![11, 12, 10, 11, 12]);
+        assert_eq!(play(clip(PlaybackMode::PingPong)), vec![11, 12, 11, 10, 11]);
+        assert_eq!(play(clip(PlaybackMode::Once)), vec![11, 12, 12, 12, 12]);
+    }
+
+    #[test]
+    fn west_falls_back_to_flipped_east() {
+        let clip = |frame| AnimationClip {
+            frames: vec![frame],
+            durations: vec![0.1],
+            mode: PlaybackMode::Loop,
+            events: HashMap::new(),
+        };
+        let mut clips = AnimationClips {
+            clips: [("IdleEast", clip(1)), ("WalkEast", clip(2))]
+                .into_iter()
+                .map(|(name, clip)| (name.to_string(), clip))
+                .collect(),
+        };
+        let frame = |clips: &AnimationClips, state| {
+            clips.clip(state, Direction::West).map(|(clip, flip)| (clip.frames[0], flip))
+        };
+        assert_eq!(frame(&clips, AnimationState::Walk), Some((2, true)));
+        assert_eq!(frame(&clips, AnimationState::Fidget), Some((1, true)));
+
+        clips.clips.insert("WalkWest".to_string(), clip(3));
+        assert_eq!(frame(&clips, AnimationState::Walk), Some((3, false)));
+    }
+
+    #[test]
+    fn clips_with_bad_durations_or_frames_are_rejected() {
+        let clip = |frames: Vec<usize>, durations: Vec<f32>| AnimationClip {
+            frames,
+            durations,
+            mode: PlaybackMode::Loop,
+            events: HashMap::new(),
+        };
+        assert_eq!(clip(vec![0, 1], vec![0.1]).check(Some(2)), Ok(()));
+        assert_eq!(clip(vec![0, 1], vec![0.1, 0.2]).check(None), Ok(()));
+        assert!(clip(vec![], vec![0.1]).check(None).is_err());
+        assert!(clip(vec![0, 1, 2], vec![0.1, 0.2]).check(None).is_err());
+
+        for duration in [0., -0.1, f32::NAN, f32::INFINITY] {
+            assert!(clip(vec![0, 1], vec![duration]).check(None).is_err(), "accepted {}", duration);
+            assert!(clip(vec![0, 1], vec![0.1, duration]).check(None).is_err(), "accepted {}", duration);
+        }
+
+        // Sprite 2 is past the end of a two sprite sheet.
+        assert!(clip(vec![1, 2], vec![0.1]).check(Some(2)).is_err());
+        assert_eq!(clip(vec![1, 2], vec![0.1]).check(None), Ok(()));
+
+        let mut with_events = clip(vec![0, 1], vec![0.1]);
+        with_events.events.insert(2, vec!["footstep".to_string()]);
+        assert!(with_events.check(None).is_err());
+    }
+
+    fn player_clips() -> AnimationClips {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/animations/player.anim.ron");
+        ron::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn player_fidgets_in_every_direction() {
+        let clips = player_clips();
+        for direction in [Direction::North, Direction::East, Direction::South, Direction::West] {
+            let (fidget, flip) = clips.clip(AnimationState::Fidget, direction).unwrap();
+            let (idle, _) = clips.clip(AnimationState::Idle, direction).unwrap();
+            assert_ne!(fidget, idle, "{:?} has no fidget", direction);
+            assert_eq!(fidget.mode, PlaybackMode::Once);
+            assert_eq!(flip, direction == Direction::West);
+        }
+    }
+
+    #[test]
+    fn walk_clips_step_on_both_feet() {
+        let clips = player_clips();
+        clips.check(None).unwrap();
+        for (name, clip) in clips.clips.iter().filter(|(name, _)| name.starts_with("Walk")) {
+            // Count the footsteps of the second cycle, the first one of a
+            // `PingPong` clip starts on its first frame instead of after it.
+            let (mut frame, mut forwards) = (0, true);
+            let mut cycles = 0;
+            let mut steps = 0;
+            while cycles < 2 {
+                if cycles == 1 {
+                    steps += clip.events_at(frame).iter().filter(|event| *event == "footstep").count();
+                }
+                if clip.ends_cycle(frame, forwards) {
+                    cycles += 1;
+                }
+                (frame, forwards) = clip.next(frame, forwards);
+            }
+            assert_eq!(steps, 2, "{} steps {} times per cycle", name, steps);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use crate::animations::clips::AnimationClips;
+use crate::Direction;
+
+/// What an animated entity is doing. Each state has a clip per direction,
+/// named after both, e.g. `WalkNorth`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    /// Played once after standing still for a while, e.g. a blink.
+    Fidget,
+    Walk,
+    /// Played once when the entity interacts with something.
+    Interact,
+}
+
+impl AnimationState {
+    /// The state to be in next, given whether the entity is moving, whether
+    /// the current clip has played to its end and whether the entity has
+    /// been idle long enough to fidget. `Interact` is only entered through
+    /// `AnimationPlayer::play`. It and `Fidget` are left once their clip has
+    /// finished.
+    pub fn next(self, moving: bool, finished: bool, rested: bool) -> Self {
+        match self {
+            Self::Interact if !finished => Self::Interact,
+            _ if moving => Self::Walk,
+            Self::Fidget if !finished => Self::Fidget,
+            Self::Idle if rested => Self::Fidget,
+            _ => Self::Idle,
+        }
+    }
+}
+
+/// Plays the clips of `clips` on the entity's `TextureAtlasSprite`. The state
+/// follows the entity's `Velocity` and `Facing`, if it has them.
+#[derive(Component, Debug)]
+pub struct AnimationPlayer {
+    pub clips: Handle<AnimationClips>,
+    pub (crate) state: AnimationState,
+    pub (crate) direction: Direction,
+    /// Position in the current clip's frame list.
+    pub (crate) frame: usize,
+    pub (crate) forwards: bool,
+    /// Set once the current clip has played through.
+    pub (crate) finished: bool,
+    /// Whether the events of the current frame have been sent.
+    pub (crate) frame_shown: bool,
+    /// Set while the current clip doesn't pass `AnimationClip::check`, so
+    /// the error is logged once.
+    pub (crate) rejected: bool,
+    pub (crate) timer: Timer,
+    /// Seconds spent in the current state.
+    pub (crate) elapsed: f32,
+    /// Seconds of standing still before fidgeting.
+    pub idle_delay: f32,
+}
+
+impl AnimationPlayer {
+    pub fn new(clips: Handle<AnimationClips>) -> Self {
+        Self {
+            clips,
+            state: AnimationState::default(),
+            direction: Direction::default(),
+            frame: 0,
+            forwards: true,
+            finished: false,
+            frame_shown: false,
+            rejected: false,
+            timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+            elapsed: 0.,
+            idle_delay: 4.,
+        }
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    /// Switches to `state` facing `direction`, from the start of its clip.
+    pub fn play(&mut self, state: AnimationState, direction: Direction) {
+        self.state = state;
+        self.direction = direction;
+        self.frame = 0;
+        self.forwards = true;
+        self.finished = false;
+        self.frame_shown = false;
+        self.timer.reset();
+        self.elapsed = 0.;
+    }
+}
+
+/// How fast the entity moved over the last frame, in pixels per second.
+/// Measured from its `Transform`, so it works however the entity is moved.
+#[derive(Component, Debug, Default)]
+pub struct Velocity {
+    pub linear: Vec2,
+    pub (crate) last_position: Option<Vec2>,
+}
+
+impl Velocity {
+    /// Slower than this counts as standing still.
+    pub const MOVING_SPEED: f32 = 1.;
+
+    pub fn is_moving(&self) -> bool {
+        self.linear.length() > Self::MOVING_SPEED
+    }
+}
+
+/// The direction an animated entity looks in.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Facing(pub Direction);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interact_plays_out_before_walking() {
+        assert_eq!(AnimationState::Idle.next(true, false, false), AnimationState::Walk);
+        assert_eq!(AnimationState::Walk.next(false, false, false), AnimationState::Idle);
+        assert_eq!(AnimationState::Interact.next(true, false, false), AnimationState::Interact);
+        assert_eq!(AnimationState::Interact.next(true, true, false), AnimationState::Walk);
+
+        assert_eq!(AnimationState::Idle.next(false, false, true), AnimationState::Fidget);
+        assert_eq!(AnimationState::Fidget.next(false, false, false), AnimationState::Fidget);
+        assert_eq!(AnimationState::Fidget.next(false, true, false), AnimationState::Idle);
+        assert_eq!(AnimationState::Fidget.next(true, false, false), AnimationState::Walk);
+    }
+}
@@ -0,0 +1,18 @@
+// File: events.rs
+//
+// Events sent by `Animator` while clips play.
+
+use bevy::prelude::*;
+
+use crate::animations::components::AnimationState;
+
+/// Sent when an `AnimationPlayer` shows a frame that has named events in its
+/// clip, e.g. `footstep`. Sent again every time the frame comes around.
+#[derive(Event, Debug, Clone)]
+pub struct AnimationEvent {
+    /// The animated entity.
+    pub entity: Entity,
+    /// The event's name in the clip file.
+    pub name: String,
+    pub state: AnimationState,
+}
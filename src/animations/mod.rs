@@ -0,0 +1,22 @@
+mod clips;
+mod components;
+mod events;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::animations::plugin::Animator;
+    pub use crate::animations::clips::{
+        AnimationAssets,
+        AnimationClip,
+        AnimationClips,
+        PlaybackMode,
+    };
+    pub use crate::animations::components::{
+        AnimationPlayer,
+        AnimationState,
+        Facing,
+        Velocity,
+    };
+    pub use crate::animations::events::AnimationEvent;
+}
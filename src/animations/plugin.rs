@@ -0,0 +1,40 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+
+use crate::animations::{
+    clips::*,
+    events::*,
+    systems::*,
+};
+use crate::player_movement::prelude::PlayerFacing;
+use crate::Player;
+
+/// Animates every entity with an `AnimationPlayer`, and gives the player one
+/// when it spawns. The clips come from `AnimationAssets`, which has to be
+/// loaded through the asset loading state. Frames with named events send an
+/// `AnimationEvent` when they are shown.
+pub struct Animator;
+
+impl Plugin for Animator {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<AnimationClips>()
+            .init_asset_loader::<AnimationClipsLoader>()
+            .add_event::<AnimationEvent>()
+            .add_systems(
+                Update,
+                (
+                    attach_player_animation::<Player>
+                        .run_if(resource_exists::<AnimationAssets>()),
+                    sync_player_facing::<Player>
+                        .run_if(resource_exists::<PlayerFacing>()),
+                    play_interact_animation::<Player>,
+                    measure_velocity,
+                    update_animation_states,
+                    advance_animations,
+                ).chain()
+            )
+            ;
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+
+use crate::animations::{
+    clips::*,
+    components::*,
+    events::*,
+};
+// Not Bevy's `AnimationPlayer`, which is for skeletal animation.
+use crate::animations::components::AnimationPlayer;
+use crate::interaction::prelude::Interacted;
+use crate::player_movement::prelude::PlayerFacing;
+
+/// Gives a newly spawned player its animations.
+pub (crate) fn attach_player_animation<Player: Component>(
+    players: Query<Entity, Added<Player>>,
+    animation_assets: Res<AnimationAssets>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).insert((
+            AnimationPlayer::new(animation_assets.player.clone()),
+            Velocity::default(),
+            Facing::default(),
+        ));
+    }
+}
+
+/// The player faces where the input points, even when they can't move.
+pub (crate) fn sync_player_facing<Player: Component>(
+    player_facing: Res<PlayerFacing>,
+    mut players: Query<&mut Facing, With<Player>>,
+) {
+    for mut facing in players.iter_mut() {
+        facing.0 = player_facing.facing;
+    }
+}
+
+pub (crate) fn play_interact_animation<Player: Component>(
+    mut interactions: EventReader<Interacted>,
+    mut players: Query<(&mut AnimationPlayer, Option<&Facing>), With<Player>>,
+) {
+    if interactions.iter().last().is_none() {
+        return;
+    }
+    for (mut animation, facing) in players.iter_mut() {
+        let direction = facing.map_or(animation.direction, |facing| facing.0);
+        animation.play(AnimationState::Interact, direction);
+    }
+}
+
+pub (crate) fn measure_velocity(
+    time: Res<Time>,
+    mut entities: Query<(&GlobalTransform, &mut Velocity)>,
+) {
+    let delta = time.delta_seconds();
+    for (gtf, mut velocity) in entities.iter_mut() {
+        let position = gtf.translation().truncate();
+        if let Some(last_position) = velocity.last_position {
+            if delta > 0. {
+                velocity.linear = (position - last_position) / delta;
+            }
+        }
+        velocity.last_position = Some(position);
+    }
+}
+
+/// Moves every `AnimationPlayer` along its state machine.
+pub (crate) fn update_animation_states(
+    time: Res<Time>,
+    mut animated: Query<(&mut AnimationPlayer, Option<&Velocity>, Option<&Facing>)>,
+) {
+    for (mut animation, velocity, facing) in animated.iter_mut() {
+        animation.elapsed += time.delta_seconds();
+        let moving = velocity.is_some_and(Velocity::is_moving);
+        let rested = animation.elapsed >= animation.idle_delay;
+        let state = animation.state.next(moving, animation.finished, rested);
+        let direction = facing.map_or(animation.direction, |facing| facing.0);
+        if state != animation.state || direction != animation.direction {
+            animation.play(state, direction);
+        }
+    }
+}
+
+/// Steps every `AnimationPlayer` through its clip and shows the current frame,
+/// flipped if the clip is the mirrored East one. Sends the frame's
+/// `AnimationEvent`s whenever a frame is shown. Clips that don't fit the
+/// entity's sprite sheet aren't played.
+pub (crate) fn advance_animations(
+    time: Res<Time>,
+    clips: Res<Assets<AnimationClips>>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut animated: Query<(Entity, &mut AnimationPlayer, &mut TextureAtlasSprite, &Handle<TextureAtlas>)>,
+    mut animation_events: EventWriter<AnimationEvent>,
+) {
+    for (entity, mut animation, mut sprite, atlas) in animated.iter_mut() {
+        let Some((clip, flip)) = clips
+            .get(&animation.clips)
+            .and_then(|clips| clips.clip(animation.state, animation.direction))
+        else {
+            continue;
+        };
+        if let Err(e) = clip.check(atlases.get(atlas).map(TextureAtlas::len)) {
+            if !animation.rejected {
+                error!("Not playing {:?}{:?} on {:?}, the clip {}", animation.state, animation.direction, entity, e);
+                animation.rejected = true;
+            }
+            continue;
+        }
+        animation.rejected = false;
+        // The clip may have lost frames since it was hot-reloaded.
+        let last = clip.frames.len() - 1;
+        animation.frame = animation.frame.min(last);
+        let duration = std::time::Duration::from_secs_f32(clip.duration(animation.frame));
+        animation.timer.set_duration(duration);
+        if animation.timer.tick(time.delta()).just_finished() {
+            let (frame, forwards) = clip.next(animation.frame, animation.forwards);
+            animation.finished |= clip.ends_cycle(animation.frame, animation.forwards);
+            // A finished `Once` clip keeps showing its last frame, it isn't
+            // shown anew.
+            if frame != animation.frame || clip.mode != PlaybackMode::Once {
+                animation.frame_shown = false;
+            }
+            animation.frame = frame;
+            animation.forwards = forwards;
+        }
+        if !animation.frame_shown {
+            animation.frame_shown = true;
+            for name in clip.events_at(animation.frame) {
+                animation_events.send(AnimationEvent {
+                    entity,
+                    name: name.clone(),
+                    state: animation.state,
+                });
+            }
+        }
+        if sprite.index != clip.frames[animation.frame] {
+            sprite.index = clip.frames[animation.frame];
+        }
+        if sprite.flip_x != flip {
+            sprite.flip_x = flip;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // Not Bevy's `AnimationClip` either.
+    use crate::animations::clips::AnimationClip;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::utils::HashMap;
+    use std::time::Duration;
+
+    /// Exactly one clip frame per update, see `clip`.
+    const FRAME_TIME: Duration = Duration::from_millis(250);
+
+    fn clip(frames: Vec<usize>, mode: PlaybackMode, events: &[(usize, &str)]) -> AnimationClip {
+        AnimationClip {
+            frames,
+            durations: vec![0.25],
+            mode,
+            events: events
+                .iter()
+                .map(|(frame, name)| (*frame, vec![name.to_string()]))
+                .collect(),
+        }
+    }
+
+    /// An app that only advances animations, with one entity playing `clip`
+    /// as its `IdleSouth` on a sheet of `atlas_len` sprites.
+    fn animation_app(clip: AnimationClip, atlas_len: usize) -> (App, Entity) {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<AnimationClips>()
+            .add_asset::<TextureAtlas>()
+            .add_event::<AnimationEvent>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .add_systems(Update, advance_animations);
+        let clips = app.world.resource_mut::<Assets<AnimationClips>>().add(AnimationClips {
+            clips: HashMap::from([("IdleSouth".to_string(), clip)]),
+        });
+        let mut atlas = TextureAtlas::new_empty(Handle::default(), Vec2::new(64. * atlas_len as f32, 64.));
+        for i in 0..atlas_len {
+            let min = Vec2::new(64. * i as f32, 0.);
+            atlas.add_texture(Rect::from_corners(min, min + 64.));
+        }
+        let atlas = app.world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+        let entity = app
+            .world
+            .spawn((AnimationPlayer::new(clips), TextureAtlasSprite::new(99), atlas))
+            .id();
+        (app, entity)
+    }
+
+    /// Runs an update and returns the names of the events it sent.
+    fn update(app: &mut App) -> Vec<String> {
+        app.update();
+        app.world
+            .resource_mut::<Events<AnimationEvent>>()
+            .drain()
+            .map(|event| event.name)
+            .collect()
+    }
+
+    #[test]
+    fn events_are_sent_once_per_frame_shown() {
+        let (mut app, _) = animation_app(clip(vec![0, 1, 2], PlaybackMode::Loop, &[(0, "a"), (2, "b")]), 3);
+        // The first update only starts the clock, and shows the first frame.
+        let sent: Vec<Vec<String>> = (0..5).map(|_| update(&mut app)).collect();
+        assert_eq!(sent, vec![vec!["a"], vec![], vec!["b"], vec!["a"], vec![]]);
+    }
+
+    #[test]
+    fn held_once_frame_sends_its_events_once() {
+        let (mut app, entity) = animation_app(clip(vec![0, 1], PlaybackMode::Once, &[(1, "done")]), 2);
+        let sent: Vec<Vec<String>> = (0..6).map(|_| update(&mut app)).collect();
+        assert_eq!(sent, vec![vec![], vec!["done"], vec![], vec![], vec![], vec![]]);
+        assert_eq!(app.world.get::<TextureAtlasSprite>(entity).unwrap().index, 1);
+    }
+
+    #[test]
+    fn clips_rejected_by_check_are_not_played() {
+        // Sprite 5 is past the end of a two sprite sheet.
+        let (mut app, entity) = animation_app(clip(vec![0, 5], PlaybackMode::Loop, &[(0, "a"), (1, "b")]), 2);
+        let sent: Vec<String> = (0..4).flat_map(|_| update(&mut app)).collect();
+        assert!(sent.is_empty());
+        assert_eq!(app.world.get::<TextureAtlasSprite>(entity).unwrap().index, 99);
+        assert!(app.world.get::<AnimationPlayer>(entity).unwrap().rejected);
+
+        let mut never_ending = clip(vec![0, 1], PlaybackMode::Loop, &[(0, "a")]);
+        never_ending.durations = vec![0.];
+        let (mut app, entity) = animation_app(never_ending, 2);
+        let sent: Vec<String> = (0..4).flat_map(|_| update(&mut app)).collect();
+        assert!(sent.is_empty());
+        assert_eq!(app.world.get::<TextureAtlasSprite>(entity).unwrap().index, 99);
+    }
+}
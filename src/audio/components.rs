@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::levels::prelude::Surface;
+
+/// Where the settings are read from on start.
+pub (crate) const AUDIO_SETTINGS_PATH: &str = "config/audio.ron";
+
+/// The LDtk level field that holds the level's background music. LDtk stores
+/// it relative to the project, which sits at the root of the assets folder.
+pub (crate) const MUSIC_FIELD: &str = "Music";
+
+/// The animation event that plays a footstep.
+pub (crate) const FOOTSTEP_EVENT: &str = "footstep";
+
+/// Volumes go from 0 (silent) to 1. The music and sound effect volumes are
+/// multiplied by `master`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    /// Seconds one level's music takes to fade into the next.
+    pub crossfade: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 0.6,
+            sfx: 0.8,
+            crossfade: 1.5,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn music_volume(&self) -> f32 {
+        (self.master * self.music).clamp(0., 1.)
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        (self.master * self.sfx).clamp(0., 1.)
+    }
+}
+
+/// Sound files are looked for with these extensions, in order.
+pub (crate) const SOUND_EXTENSIONS: [&str; 2] = ["ogg", "wav"];
+
+/// A sound played in response to gameplay. Each is read from the first of its
+/// `paths` that exists in the assets folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    KeyPickedUp,
+    LockOpened,
+    /// The player walked into something.
+    Blocked,
+    Footstep(Surface),
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 7] = [
+        Self::KeyPickedUp,
+        Self::LockOpened,
+        Self::Blocked,
+        Self::Footstep(Surface::Grass),
+        Self::Footstep(Surface::Sand),
+        Self::Footstep(Surface::Soil),
+        Self::Footstep(Surface::Water),
+    ];
+
+    /// The sound's file name in `assets/audio`, without an extension.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::KeyPickedUp => "key_picked_up",
+            Self::LockOpened => "lock_opened",
+            Self::Blocked => "blocked",
+            Self::Footstep(Surface::Grass) => "footstep_grass",
+            Self::Footstep(Surface::Sand) => "footstep_sand",
+            Self::Footstep(Surface::Soil) => "footstep_soil",
+            Self::Footstep(Surface::Water) => "footstep_water",
+        }
+    }
+
+    /// Where the sound may be in the assets folder, one path per extension
+    /// in `SOUND_EXTENSIONS`.
+    pub fn paths(&self) -> impl Iterator<Item = String> {
+        let name = self.name();
+        SOUND_EXTENSIONS.into_iter().map(move |extension| format!("audio/{}.{}", name, extension))
+    }
+}
+
+/// The sound effects that were found in the assets folder.
+#[derive(Resource, Default)]
+pub (crate) struct SoundEffects {
+    pub (crate) handles: HashMap<SoundEffect, Handle<AudioSource>>,
+}
+
+/// A looping music track. Only one is `fading_out == false` at a time, the
+/// others fade out and are despawned.
+#[derive(Component, Debug)]
+pub (crate) struct MusicTrack {
+    pub (crate) path: String,
+    /// How far the track has faded in, from 0 to 1.
+    pub (crate) fade: f32,
+    pub (crate) fading_out: bool,
+}
+
+impl MusicTrack {
+    pub (crate) fn new(path: String) -> Self {
+        Self {
+            path,
+            fade: 0.,
+            fading_out: false,
+        }
+    }
+
+    /// Fades the track in or out over `crossfade` seconds. Returns whether it
+    /// has faded out completely.
+    pub (crate) fn advance(&mut self, delta: f32, crossfade: f32) -> bool {
+        let step = if crossfade > 0. { delta / crossfade } else { 1. };
+        let target = if self.fading_out { 0. } else { 1. };
+        self.fade = if self.fade < target {
+            (self.fade + step).min(target)
+        } else {
+            (self.fade - step).max(target)
+        };
+        self.fading_out && self.fade == 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_fade_over_the_crossfade() {
+        let mut track = MusicTrack::new("audio/music.ogg".to_string());
+        assert!(!track.advance(0.5, 1.));
+        assert_eq!(track.fade, 0.5);
+        assert!(!track.advance(1., 1.));
+        assert_eq!(track.fade, 1.);
+
+        track.fading_out = true;
+        assert!(!track.advance(0.75, 1.));
+        assert_eq!(track.fade, 0.25);
+        assert!(track.advance(0.75, 1.));
+
+        let mut instant = MusicTrack::new("audio/music.ogg".to_string());
+        instant.advance(0.01, 0.);
+        assert_eq!(instant.fade, 1.);
+    }
+}
@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::audio::plugin::SoundPlugin;
+    pub use crate::audio::components::{AudioSettings, SoundEffect};
+}
@@ -0,0 +1,54 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use std::marker::PhantomData;
+
+use crate::audio::{
+    components::*,
+    systems::*,
+};
+
+/// Plays each level's background music, set in its `Music` field in LDtk, and
+/// crossfades to the next level's music on a level switch. Sound effects are
+/// played for picking up keys, opening locks, the player's footsteps on each
+/// `Surface` and walking into walls. Volumes are read from
+/// `config/audio.ron` on start.
+#[derive(Default)]
+pub struct SoundPlugin<PlayerComponent: Component + Default> {
+    player: PhantomData<PlayerComponent>,
+}
+
+impl<PlayerComponent> Plugin for SoundPlugin<PlayerComponent>
+where
+PlayerComponent: Component + Default,
+{
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(AudioSettings::default())
+            .init_resource::<SoundEffects>()
+            .add_systems(
+                Startup,
+                (
+                    load_audio_settings,
+                    load_sound_effects,
+                )
+            )
+            // After all gameplay, so every event of this frame is heard in
+            // this frame.
+            .add_systems(
+                PostUpdate,
+                (
+                    (
+                        play_level_music,
+                        fade_music,
+                    ).chain(),
+                    play_key_and_lock_sounds,
+                    play_footsteps::<PlayerComponent>,
+                    play_blocked_sound,
+                )
+                    .before(TransformSystem::TransformPropagate)
+            )
+            ;
+    }
+}
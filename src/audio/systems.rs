@@ -0,0 +1,292 @@
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::Level;
+use bevy_ecs_ldtk::prelude::*;
+use std::fs;
+use std::path::Path;
+
+use crate::animations::prelude::AnimationEvent;
+use crate::audio::components::*;
+use crate::levels::prelude::*;
+use crate::player_movement::prelude::{MovementBlocked, PlayerHitbox};
+use crate::utils::translation_to_grid_coords;
+
+pub (crate) fn load_audio_settings(mut commands: Commands) {
+    let path = Path::new(AUDIO_SETTINGS_PATH);
+    if !path.exists() {
+        info!("No {} found, using default audio settings", AUDIO_SETTINGS_PATH);
+        return;
+    }
+    let loaded = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str::<AudioSettings>(&contents).map_err(|e| e.to_string()));
+    match loaded {
+        Ok(settings) => commands.insert_resource(settings),
+        Err(e) => error!("Could not read {}, using default audio settings: {}", AUDIO_SETTINGS_PATH, e),
+    }
+}
+
+/// Loads every `SoundEffect` that has a file, so that missing sounds are
+/// skipped quietly instead of failing to load each time they play.
+pub (crate) fn load_sound_effects(asset_server: Res<AssetServer>, mut sounds: ResMut<SoundEffects>) {
+    for effect in SoundEffect::ALL {
+        match effect.paths().find(|path| Path::new("assets").join(path).exists()) {
+            Some(path) => {
+                sounds.handles.insert(effect, asset_server.load(path.as_str()));
+            },
+            None => info!("No audio/{}.* found, {:?} is silent", effect.name(), effect),
+        }
+    }
+}
+
+fn play_sound(
+    effect: SoundEffect,
+    sounds: &SoundEffects,
+    settings: &AudioSettings,
+    commands: &mut Commands,
+) {
+    let Some(source) = sounds.handles.get(&effect) else {
+        return;
+    };
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(settings.sfx_volume())),
+    });
+}
+
+fn level_music(level: &Level) -> Option<String> {
+    level.get_maybe_file_path_field(MUSIC_FIELD).ok().cloned().flatten()
+}
+
+/// Picks the music of the level the player is heading to. The crossfade
+/// starts as soon as a switch is requested, so it plays under the transition.
+/// Levels without music keep the track that is playing.
+pub (crate) fn play_level_music(
+    mut switch_requests: EventReader<LevelSwitchRequested>,
+    mut entered: EventReader<PlayerEnteredLevel>,
+    ldtk_project_entities: Query<&Handle<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
+    mut tracks: Query<&mut MusicTrack>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let selections: Vec<LevelSelection> = switch_requests
+        .iter()
+        .map(|request| LevelSelection::Identifier(request.to.clone()))
+        .chain(entered.iter().map(|entered| LevelSelection::Iid(entered.level_iid.clone())))
+        .collect();
+    let Some(selection) = selections.last() else {
+        return;
+    };
+    let Some(ldtk_project) = ldtk_project_entities
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+    else {
+        return;
+    };
+    let Some(path) = ldtk_project.get_level(selection).and_then(level_music) else {
+        return;
+    };
+
+    if tracks.iter().any(|track| track.path == path && !track.fading_out) {
+        return;
+    }
+    let mut resumed = false;
+    for mut track in tracks.iter_mut() {
+        // Coming back before the old track has faded out fades it back in.
+        track.fading_out = track.path != path;
+        resumed |= track.path == path;
+    }
+    if resumed {
+        return;
+    }
+    info!("Playing {}", path);
+    commands.spawn((
+        AudioBundle {
+            source: asset_server.load(path.as_str()),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.)),
+        },
+        MusicTrack::new(path),
+    ));
+}
+
+/// Crossfades the music tracks and applies the music volume.
+pub (crate) fn fade_music(
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    settings: Res<AudioSettings>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut track, sink) in tracks.iter_mut() {
+        if track.advance(time.delta_seconds(), settings.crossfade) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // The sink only exists once the track has loaded.
+        if let Some(sink) = sink {
+            sink.set_volume(track.fade * settings.music_volume());
+        }
+    }
+}
+
+pub (crate) fn play_key_and_lock_sounds(
+    mut key_picked_up: EventReader<KeyPickedUp>,
+    mut lock_opened: EventReader<LockOpened>,
+    sounds: Res<SoundEffects>,
+    settings: Res<AudioSettings>,
+    mut commands: Commands,
+) {
+    for _ in key_picked_up.iter() {
+        play_sound(SoundEffect::KeyPickedUp, &sounds, &settings, &mut commands);
+    }
+    for _ in lock_opened.iter() {
+        play_sound(SoundEffect::LockOpened, &sounds, &settings, &mut commands);
+    }
+}
+
+/// Plays a footstep for the ground under the player's feet whenever their
+/// walk animation sends `FOOTSTEP_EVENT`.
+pub (crate) fn play_footsteps<Player: Component>(
+    mut animation_events: EventReader<AnimationEvent>,
+    players: Query<&Transform, With<Player>>,
+    hitbox: Res<PlayerHitbox>,
+    terrain: Res<Terrain>,
+    sounds: Res<SoundEffects>,
+    settings: Res<AudioSettings>,
+    mut commands: Commands,
+) {
+    for event in animation_events.iter().filter(|event| event.name == FOOTSTEP_EVENT) {
+        let Ok(tf) = players.get(event.entity) else {
+            continue;
+        };
+        let (min, max) = hitbox.bounds_at(tf.translation.truncate());
+        let feet = translation_to_grid_coords((min + max) / 2.);
+        if let Some(surface) = terrain.at(&feet).surface {
+            play_sound(SoundEffect::Footstep(surface), &sounds, &settings, &mut commands);
+        }
+    }
+}
+
+/// Plays a bump when the player walks into something. Pushing against it
+/// keeps sending `MovementBlocked`, so only the first frame is heard.
+pub (crate) fn play_blocked_sound(
+    mut blocked: EventReader<MovementBlocked>,
+    mut was_blocked: Local<bool>,
+    sounds: Res<SoundEffects>,
+    settings: Res<AudioSettings>,
+    mut commands: Commands,
+) {
+    let is_blocked = blocked.iter().count() > 0;
+    if is_blocked && !*was_blocked {
+        play_sound(SoundEffect::Blocked, &sounds, &settings, &mut commands);
+    }
+    *was_blocked = is_blocked;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::audio::AudioSource;
+    use bevy_ecs_ldtk::ldtk::LdtkJson;
+
+    use crate::player_movement::prelude::MovementBlocked;
+    use crate::Direction;
+
+    /// An app with the music and bump systems and `farms.ldtk` loaded, with
+    /// `edit` applied to it first.
+    fn audio_app(edit: impl FnOnce(&mut LdtkJson)) -> App {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/farms.ldtk");
+        let mut project: LdtkJson = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        edit(&mut project);
+
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkAsset>()
+            .add_asset::<AudioSource>()
+            .insert_resource(AudioSettings::default())
+            .insert_resource(SoundEffects {
+                handles: [(SoundEffect::Blocked, Handle::default())].into_iter().collect(),
+            })
+            .add_event::<LevelSwitchRequested>()
+            .add_event::<PlayerEnteredLevel>()
+            .add_event::<MovementBlocked>()
+            .add_systems(Update, (play_level_music, play_blocked_sound));
+        let ldtk_asset = app.world.resource_mut::<Assets<LdtkAsset>>().add(LdtkAsset {
+            project,
+            tileset_map: default(),
+            level_map: default(),
+            int_grid_image_handle: None,
+        });
+        app.world.spawn(ldtk_asset);
+        app
+    }
+
+    fn switch_to(app: &mut App, level: &str) {
+        app.world.send_event(LevelSwitchRequested {
+            to: level.to_string(),
+            entrance: "West".to_string(),
+        });
+        app.update();
+    }
+
+    /// Every music track, and whether it is fading out, by path.
+    fn tracks(app: &mut App) -> Vec<(String, bool)> {
+        let mut query = app.world.query::<&MusicTrack>();
+        let mut tracks: Vec<_> = query
+            .iter(&app.world)
+            .map(|track| (track.path.clone(), track.fading_out))
+            .collect();
+        tracks.sort();
+        tracks
+    }
+
+    fn sounds_played(app: &mut App) -> usize {
+        let mut query = app.world.query_filtered::<(), (With<Handle<AudioSource>>, Without<MusicTrack>)>();
+        query.iter(&app.world).count()
+    }
+
+    #[test]
+    fn music_crossfades_to_the_level_being_switched_to() {
+        let fields = "audio/music/fields.wav".to_string();
+        let trove = "audio/music/trove.wav".to_string();
+        let mut app = audio_app(|project| {
+            let finale = project.levels.iter_mut().find(|level| level.identifier == "Finale").unwrap();
+            finale.field_instances.clear();
+        });
+        switch_to(&mut app, "Wheat_Fields");
+        assert_eq!(tracks(&mut app), vec![(fields.clone(), false)]);
+
+        // Crossroads plays the same track, it keeps playing.
+        switch_to(&mut app, "Crossroads");
+        assert_eq!(tracks(&mut app), vec![(fields.clone(), false)]);
+
+        switch_to(&mut app, "Treasure_Trove");
+        assert_eq!(tracks(&mut app), vec![(fields.clone(), true), (trove.clone(), false)]);
+
+        // Going back before the fade is over fades the old track back in
+        // instead of starting it again.
+        switch_to(&mut app, "Crossroads");
+        assert_eq!(tracks(&mut app), vec![(fields.clone(), false), (trove.clone(), true)]);
+
+        // Finale has no music here, so the current track carries on.
+        switch_to(&mut app, "Finale");
+        assert_eq!(tracks(&mut app), vec![(fields, false), (trove, true)]);
+    }
+
+    #[test]
+    fn blocked_sound_plays_once_per_bump() {
+        let mut app = audio_app(|_| {});
+        for _ in 0..3 {
+            app.world.send_event(MovementBlocked { direction: Direction::North });
+            app.update();
+        }
+        assert_eq!(sounds_played(&mut app), 1);
+
+        app.update();
+        app.world.send_event(MovementBlocked { direction: Direction::North });
+        app.update();
+        assert_eq!(sounds_played(&mut app), 2);
+    }
+}
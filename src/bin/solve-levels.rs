@@ -0,0 +1,90 @@
+// File: solve-levels.rs
+//
+// Checks that the game can be finished, and lists the steps that make it
+// unwinnable:
+//
+//     cargo run --bin solve-levels [path/to/project.ldtk]
+//
+// Exits with a failure status if the goal can't be reached or a soft-lock was
+// found.
+
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use pixel_farms::levels::prelude::{
+    solve,
+    validate_project,
+    IntGridSemantics,
+    Step,
+    GOAL_LEVEL,
+    INT_GRID_SEMANTICS_PATH,
+    MAX_STATES,
+};
+use std::process::ExitCode;
+
+const DEFAULT_PROJECT: &str = "assets/farms.ldtk";
+
+fn print_steps(steps: &[Step]) {
+    for (i, step) in steps.iter().enumerate() {
+        println!("  {}. {}", i + 1, step);
+    }
+}
+
+fn main() -> ExitCode {
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_PROJECT.to_string());
+    let project: LdtkJson = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(project) => project,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    // Broken links change what can be reached, so point them out first.
+    for problem in validate_project(&project) {
+        println!("warning: {}", problem);
+    }
+
+    let semantics = if std::path::Path::new(INT_GRID_SEMANTICS_PATH).exists() {
+        match IntGridSemantics::load(INT_GRID_SEMANTICS_PATH) {
+            Ok(semantics) => semantics,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", INT_GRID_SEMANTICS_PATH, e);
+                return ExitCode::FAILURE;
+            },
+        }
+    } else {
+        IntGridSemantics::default()
+    };
+
+    let report = match solve(&project, &semantics) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Could not solve {}: {}", path, e);
+            return ExitCode::FAILURE;
+        },
+    };
+    println!("Searched {} puzzle states", report.states);
+    if report.truncated {
+        println!("warning: stopped after {} states, the results are incomplete", MAX_STATES);
+    }
+
+    match report.solution.as_ref() {
+        Some(steps) => {
+            println!("{} can be reached. Shortest key order:", GOAL_LEVEL);
+            print_steps(steps);
+        },
+        None => println!("{} can not be reached!", GOAL_LEVEL),
+    }
+    for steps in report.soft_locks.iter() {
+        println!("Soft-lock, {} can no longer be reached after:", GOAL_LEVEL);
+        print_steps(steps);
+    }
+
+    if report.solution.is_some() && report.soft_locks.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
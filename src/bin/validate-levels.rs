@@ -0,0 +1,40 @@
+// File: validate-levels.rs
+//
+// Runs the same checks as the game does when loading levels, without starting
+// the game:
+//
+//     cargo run --bin validate-levels [path/to/project.ldtk]
+//
+// Exits with a failure status if any problem was found.
+
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use pixel_farms::levels::prelude::validate_project;
+use std::process::ExitCode;
+
+const DEFAULT_PROJECT: &str = "assets/farms.ldtk";
+
+fn main() -> ExitCode {
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_PROJECT.to_string());
+    let project: LdtkJson = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(project) => project,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let problems = validate_project(&project);
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    if problems.is_empty() {
+        println!("{}: no problems found", path);
+        ExitCode::SUCCESS
+    } else {
+        println!("{}: {} problem(s) found", path, problems.len());
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+/// The camera that `CameraPlugin` moves.
+#[derive(Default, Component)]
+pub struct MainCamera;
+
+/// How the camera follows the player. If you add this resource to your app,
+/// it will override the defaults.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CameraSettings {
+    /// Size in pixels of the box around the centre of the screen that the
+    /// player can move in without the camera following.
+    pub dead_zone: Vec2,
+    /// How quickly the camera catches up, per second. Higher is snappier.
+    pub smoothing: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::new(64., 48.),
+            smoothing: 6.,
+        }
+    }
+}
+
+/// Set when the player was placed in a level, so the camera jumps to them
+/// instead of sliding across the new level.
+#[derive(Resource, Default)]
+pub (crate) struct SnapCamera(pub (crate) bool);
@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::camera::plugin::CameraPlugin;
+    pub use crate::camera::components::{CameraSettings, MainCamera};
+}
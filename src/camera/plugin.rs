@@ -0,0 +1,45 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use std::marker::PhantomData;
+
+use crate::camera::{
+    components::*,
+    systems::*,
+};
+use crate::levels::prelude::LevelBounds;
+
+/// Makes the `MainCamera` follow the `PlayerComponent` with a dead zone and
+/// smoothing, keeping it inside the current level's `LevelBounds`. Levels
+/// smaller than the screen are centred, and the camera jumps straight to the
+/// player when a level spawns. Walking into a neighbouring level of a seamless
+/// world scrolls the camera over instead.
+#[derive(Default)]
+pub struct CameraPlugin<PlayerComponent: Component + Default> {
+    player: PhantomData<PlayerComponent>,
+}
+
+impl<PlayerComponent> Plugin for CameraPlugin<PlayerComponent>
+where
+PlayerComponent: Component + Default,
+{
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraSettings>()
+            .insert_resource(SnapCamera(true))
+            // After all gameplay, so the camera sees where the player ended up
+            // this frame.
+            .add_systems(
+                PostUpdate,
+                (
+                    snap_on_level_enter,
+                    follow_player::<PlayerComponent>,
+                )
+                    .chain()
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(resource_exists::<LevelBounds>())
+            )
+            ;
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::camera::components::*;
+use crate::levels::prelude::{LevelBounds, PlayerEnteredLevel};
+
+/// Where the camera has to be for `view` (its visible size) to stay inside
+/// `bounds`. Levels smaller than the view are centred instead.
+pub (crate) fn clamp_to_bounds(position: Vec2, view: Vec2, bounds: Rect) -> Vec2 {
+    let clamp_axis = |position: f32, view: f32, min: f32, max: f32| {
+        if max - min <= view {
+            (min + max) / 2.
+        } else {
+            position.clamp(min + view / 2., max - view / 2.)
+        }
+    };
+    Vec2::new(
+        clamp_axis(position.x, view.x, bounds.min.x, bounds.max.x),
+        clamp_axis(position.y, view.y, bounds.min.y, bounds.max.y),
+    )
+}
+
+/// Moves `camera` just far enough that `target` is inside the dead zone.
+fn follow_dead_zone(camera: Vec2, target: Vec2, dead_zone: Vec2) -> Vec2 {
+    let offset = target - camera;
+    let half = dead_zone / 2.;
+    camera + offset - offset.clamp(-half, half)
+}
+
+/// Snaps when the player is placed in a level, but not when they walk into a
+/// neighbouring one, so the camera scrolls over instead.
+pub (crate) fn snap_on_level_enter(
+    mut player_entered: EventReader<PlayerEnteredLevel>,
+    mut snap: ResMut<SnapCamera>,
+) {
+    for entered in player_entered.iter() {
+        if !entered.scrolled {
+            snap.0 = true;
+        }
+    }
+}
+
+pub (crate) fn follow_player<Player: Component>(
+    player: Query<&Transform, (With<Player>, Without<MainCamera>)>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    settings: Res<CameraSettings>,
+    bounds: Res<LevelBounds>,
+    mut snap: ResMut<SnapCamera>,
+    time: Res<Time>,
+) {
+    let (Ok(player), Ok((mut camera, projection))) = (player.get_single(), camera.get_single_mut()) else {
+        return;
+    };
+    let view = projection.area.size();
+    let current = camera.translation.truncate();
+    let target = player.translation.truncate();
+
+    // Clamping before smoothing makes the camera slide over when the bounds
+    // move to a neighbouring level, rather than jump.
+    let position = if snap.0 {
+        snap.0 = false;
+        clamp_to_bounds(target, view, bounds.rect)
+    } else {
+        let desired = follow_dead_zone(current, target, settings.dead_zone);
+        let desired = clamp_to_bounds(desired, view, bounds.rect);
+        let t = 1. - (-settings.smoothing * time.delta_seconds()).exp();
+        current.lerp(desired, t)
+    };
+    camera.translation = position.extend(camera.translation.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_stays_inside_level_and_centres_small_ones() {
+        let view = Vec2::new(640., 480.);
+        let big = Rect::new(0., 0., 1280., 960.);
+        assert_eq!(clamp_to_bounds(Vec2::new(10., 10.), view, big), Vec2::new(320., 240.));
+        assert_eq!(clamp_to_bounds(Vec2::new(700., 500.), view, big), Vec2::new(700., 500.));
+        assert_eq!(clamp_to_bounds(Vec2::new(2000., 2000.), view, big), Vec2::new(960., 720.));
+
+        let crossroads = Rect::new(0., 0., 544., 480.);
+        assert_eq!(clamp_to_bounds(Vec2::new(100., 100.), view, crossroads), Vec2::new(272., 240.));
+    }
+
+    #[test]
+    fn dead_zone_only_follows_past_its_edge() {
+        let dead_zone = Vec2::new(64., 48.);
+        assert_eq!(follow_dead_zone(Vec2::ZERO, Vec2::new(20., -10.), dead_zone), Vec2::ZERO);
+        assert_eq!(follow_dead_zone(Vec2::ZERO, Vec2::new(50., -40.), dead_zone), Vec2::new(18., -16.));
+    }
+}
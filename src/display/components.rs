@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Resolution the game is drawn at before being scaled up to the window.
+pub const RENDER_SIZE: UVec2 = UVec2::new(320, 240);
+
+/// Where the settings are read from on start and written to when changed.
+pub (crate) const DISPLAY_SETTINGS_PATH: &str = "config/display.ron";
+
+/// Largest window scale that F10 cycles through.
+pub (crate) const MAX_WINDOW_SCALE: u32 = 4;
+
+/// Render layer of the upscaled game image and the camera that shows it, so
+/// the game camera doesn't draw it into itself.
+pub (crate) const UPSCALE_LAYER: u8 = 1;
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Draw the game at `RENDER_SIZE` and scale it up by whole numbers, with
+    /// black bars around it. Only read on start.
+    pub pixel_perfect: bool,
+    /// The window is `RENDER_SIZE` times this when not fullscreen.
+    pub window_scale: u32,
+    pub fullscreen: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            pixel_perfect: true,
+            window_scale: 2,
+            fullscreen: false,
+        }
+    }
+}
+
+/// The image the game camera draws into when `pixel_perfect` is on.
+#[derive(Resource)]
+pub (crate) struct LowResTarget(pub (crate) Handle<Image>);
+
+/// The sprite showing `LowResTarget` in the window.
+#[derive(Component)]
+pub (crate) struct UpscaledView;
@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::display::plugin::DisplayPlugin;
+    pub use crate::display::components::{DisplaySettings, RENDER_SIZE};
+}
@@ -0,0 +1,40 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+
+use crate::display::{
+    components::*,
+    systems::*,
+};
+
+/// Draws the game at `RENDER_SIZE` into an image and shows it scaled up by
+/// the largest whole number that fits the window, so the pixel art stays
+/// crisp at any resolution. The window scale and fullscreen mode are read
+/// from `config/display.ron` on start and can be changed with F10 and F11.
+#[derive(Default)]
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(DisplaySettings::default())
+            .add_systems(
+                Startup,
+                (
+                    load_display_settings,
+                    apply_deferred,
+                    setup_low_res_target,
+                ).chain()
+            )
+            .add_systems(
+                Update,
+                (
+                    change_display_settings,
+                    apply_window_settings,
+                    retarget_main_camera,
+                    fit_upscaled_view,
+                ).chain()
+            )
+            ;
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d,
+    TextureDescriptor,
+    TextureDimension,
+    TextureFormat,
+    TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use bevy::window::{PrimaryWindow, WindowMode};
+use std::fs;
+use std::path::Path;
+
+use crate::camera::prelude::MainCamera;
+use crate::display::components::*;
+
+pub (crate) fn load_display_settings(mut commands: Commands) {
+    let path = Path::new(DISPLAY_SETTINGS_PATH);
+    if !path.exists() {
+        info!("No {} found, using default display settings", DISPLAY_SETTINGS_PATH);
+        return;
+    }
+    let loaded = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str::<DisplaySettings>(&contents).map_err(|e| e.to_string()));
+    match loaded {
+        Ok(settings) => commands.insert_resource(settings),
+        Err(e) => error!("Could not read {}, using default display settings: {}", DISPLAY_SETTINGS_PATH, e),
+    }
+}
+
+fn write_display_settings(settings: &DisplaySettings) {
+    let path = Path::new(DISPLAY_SETTINGS_PATH);
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|contents| fs::write(path, contents).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        error!("Could not write {}: {}", DISPLAY_SETTINGS_PATH, e);
+    }
+}
+
+/// Creates the low resolution image the game is drawn into, and a second
+/// camera that shows it scaled up in the window.
+pub (crate) fn setup_low_res_target(
+    settings: Res<DisplaySettings>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    if !settings.pixel_perfect {
+        return;
+    }
+    let size = Extent3d {
+        width: RENDER_SIZE.x,
+        height: RENDER_SIZE.y,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("low_res_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let handle = images.add(image);
+
+    commands.spawn((
+        SpriteBundle {
+            texture: handle.clone(),
+            ..default()
+        },
+        UpscaledView,
+        RenderLayers::layer(UPSCALE_LAYER),
+    ));
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                // Draw after the game camera has filled the image.
+                order: 1,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+            },
+            ..default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+    ));
+    commands.insert_resource(LowResTarget(handle));
+}
+
+/// Points the game camera at the low resolution image. UI is left to the
+/// upscaling camera, so text is drawn at the window's resolution.
+pub (crate) fn retarget_main_camera(
+    target: Option<Res<LowResTarget>>,
+    mut cameras: Query<(Entity, &mut Camera), Added<MainCamera>>,
+    mut commands: Commands,
+) {
+    let Some(target) = target else {
+        return;
+    };
+    for (entity, mut camera) in cameras.iter_mut() {
+        camera.target = RenderTarget::Image(target.0.clone());
+        commands.entity(entity).insert(UiCameraConfig { show_ui: false });
+    }
+}
+
+/// Largest whole number `RENDER_SIZE` can be scaled by and still fit in
+/// `physical`, but never less than 1.
+pub (crate) fn upscale_factor(physical: UVec2) -> u32 {
+    (physical / RENDER_SIZE).min_element().max(1)
+}
+
+/// Scales the game image by the largest whole number that fits the window.
+pub (crate) fn fit_upscaled_view(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut views: Query<&mut Transform, With<UpscaledView>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    // Work in physical pixels, so that the art stays crisp on high DPI screens.
+    let physical = UVec2::new(window.physical_width(), window.physical_height());
+    let factor = upscale_factor(physical);
+    let scale = factor as f32 / window.scale_factor() as f32;
+    for mut tf in views.iter_mut() {
+        tf.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+pub (crate) fn apply_window_settings(
+    settings: Res<DisplaySettings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let size = (RENDER_SIZE * settings.window_scale.max(1)).as_vec2();
+    window.resolution.set(size.x, size.y);
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+}
+
+/// F10 cycles the window scale, F11 toggles fullscreen. Changes are saved.
+pub (crate) fn change_display_settings(
+    input: Res<Input<KeyCode>>,
+    mut settings: ResMut<DisplaySettings>,
+) {
+    if input.just_pressed(KeyCode::F10) {
+        settings.window_scale = settings.window_scale % MAX_WINDOW_SCALE + 1;
+        info!("Window scale {}", settings.window_scale);
+    } else if input.just_pressed(KeyCode::F11) {
+        settings.fullscreen = !settings.fullscreen;
+        info!("Fullscreen {}", settings.fullscreen);
+    } else {
+        return;
+    }
+    write_display_settings(&settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upscale_factor_is_the_largest_whole_number_that_fits() {
+        assert_eq!(upscale_factor(UVec2::new(640, 480)), 2);
+        assert_eq!(upscale_factor(UVec2::new(1920, 1080)), 4);
+        assert_eq!(upscale_factor(UVec2::new(2560, 1440)), 6);
+        // Wide windows are limited by their height, tall ones by their width.
+        assert_eq!(upscale_factor(UVec2::new(3000, 500)), 2);
+        assert_eq!(upscale_factor(UVec2::new(700, 2000)), 2);
+        // Too small to fit even once still shows the image, cropped.
+        assert_eq!(upscale_factor(UVec2::new(200, 100)), 1);
+    }
+}
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::levels::prelude::LockKeyColor;
+
+/// Size the key icons are drawn at 1x, the size of a tile in the key tileset.
+pub (crate) const ICON_SIZE: f32 = 16.;
+
+/// Window height that the icons are drawn at 1x for. The icon scale is the
+/// largest whole multiple of this that fits, so the pixel art stays crisp.
+pub (crate) const BASE_HEIGHT: f32 = 240.;
+
+pub (crate) const APPEAR_SECONDS: f32 = 0.25;
+pub (crate) const DISAPPEAR_SECONDS: f32 = 0.2;
+
+/// Atlas of every tileset the key icons come from, by LDtk tileset uid.
+#[derive(Resource, Default)]
+pub (crate) struct IconAtlases(pub (crate) HashMap<i32, Handle<TextureAtlas>>);
+
+/// Integer scale the HUD is currently drawn at.
+#[derive(Resource)]
+pub (crate) struct HudScale(pub (crate) f32);
+
+impl Default for HudScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl HudScale {
+    pub (crate) fn from_window_height(height: f32) -> Self {
+        Self((height / BASE_HEIGHT).floor().max(1.))
+    }
+
+    pub (crate) fn icon_size(&self) -> Val {
+        Val::Px(ICON_SIZE * self.0)
+    }
+}
+
+/// The row that holds one icon per carried key.
+#[derive(Component, Default)]
+pub (crate) struct KeyInventory;
+
+#[derive(Component)]
+pub (crate) struct KeyIcon {
+    pub (crate) color: LockKeyColor,
+}
+
+#[derive(Component)]
+pub (crate) enum IconAnimation {
+    /// Pops in after the key is picked up.
+    Appearing(Timer),
+    /// Shrinks away after the key is used up, then despawns.
+    Disappearing(Timer),
+}
@@ -0,0 +1,7 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::hud::plugin::HudPlugin;
+}
@@ -0,0 +1,34 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+
+use crate::hud::components::IconAtlases;
+use crate::hud::systems::*;
+use crate::levels::prelude::{CarriedKeys, KeyTypes};
+
+/// Shows an icon for every key in `CarriedKeys` in the top left corner, using
+/// the key icons from `KeyTypes`. Icons
+/// pop in when a key is picked up and shrink away when it is used up, and the
+/// HUD is rescaled in whole steps when the window is resized.
+#[derive(Default)]
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<IconAtlases>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(
+                Update,
+                (
+                    rescale_hud,
+                    sync_key_icons,
+                    animate_icons,
+                )
+                    .chain()
+                    .run_if(resource_exists::<CarriedKeys>())
+                    .run_if(resource_exists::<KeyTypes>())
+            )
+            ;
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::{PrimaryWindow, WindowResized};
+
+use crate::hud::components::*;
+use crate::levels::prelude::{CarriedKeys, KeyTypes, LockKeyColor};
+
+pub (crate) fn setup_hud(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let scale = window
+        .get_single()
+        .map_or_else(|_| HudScale::default(), |w| HudScale::from_window_height(w.height()));
+    commands.spawn((
+        NodeBundle {
+            style: inventory_style(&scale),
+            ..default()
+        },
+        KeyInventory,
+    ));
+    commands.insert_resource(scale);
+}
+
+fn inventory_style(scale: &HudScale) -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(4. * scale.0),
+        left: Val::Px(4. * scale.0),
+        flex_direction: FlexDirection::Row,
+        column_gap: Val::Px(2. * scale.0),
+        ..default()
+    }
+}
+
+fn icon_style(scale: &HudScale) -> Style {
+    Style {
+        width: scale.icon_size(),
+        height: scale.icon_size(),
+        ..default()
+    }
+}
+
+pub (crate) fn rescale_hud(
+    mut resized: EventReader<WindowResized>,
+    mut scale: ResMut<HudScale>,
+    mut inventory: Query<&mut Style, With<KeyInventory>>,
+    mut icons: Query<&mut Style, (With<KeyIcon>, Without<KeyInventory>)>,
+) {
+    let Some(event) = resized.iter().last() else {
+        return;
+    };
+    let new_scale = HudScale::from_window_height(event.height);
+    if new_scale.0 == scale.0 {
+        return;
+    }
+    *scale = new_scale;
+    for mut style in inventory.iter_mut() {
+        *style = inventory_style(&scale);
+    }
+    for mut style in icons.iter_mut() {
+        *style = icon_style(&scale);
+    }
+}
+
+/// Keeps one icon per carried key, so two red keys show two red icons. Icons
+/// are kept in the order of `KeyTypes`.
+pub (crate) fn sync_key_icons(
+    carried_keys: Res<CarriedKeys>,
+    key_types: Res<KeyTypes>,
+    mut atlases: ResMut<IconAtlases>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    scale: Res<HudScale>,
+    inventory: Query<(Entity, Option<&Children>), With<KeyInventory>>,
+    icons: Query<(Entity, &KeyIcon, Option<&IconAnimation>)>,
+    mut commands: Commands,
+) {
+    if !carried_keys.is_changed() {
+        return;
+    }
+    let Ok((inventory, children)) = inventory.get_single() else {
+        return;
+    };
+
+    let mut shown: HashMap<LockKeyColor, Vec<Entity>> = HashMap::new();
+    for (entity, icon, animation) in icons.iter() {
+        if !matches!(animation, Some(IconAnimation::Disappearing(_))) {
+            shown.entry(icon.color.clone()).or_default().push(entity);
+        }
+    }
+
+    for (color, entities) in shown.iter() {
+        let carried = carried_keys.count(color) as usize;
+        for entity in entities.iter().skip(carried) {
+            commands.entity(*entity).insert(IconAnimation::Disappearing(
+                Timer::from_seconds(DISAPPEAR_SECONDS, TimerMode::Once),
+            ));
+        }
+    }
+
+    let mut new_icons: Vec<LockKeyColor> = carried_keys
+        .keys
+        .iter()
+        .flat_map(|(color, count)| {
+            let already_shown = shown.get(color).map_or(0, |e| e.len());
+            std::iter::repeat_n(color.clone(), (*count as usize).saturating_sub(already_shown))
+        })
+        .collect();
+    new_icons.sort_by_key(|color| key_types.order(color));
+
+    // Order of every icon in the row, to find where the new ones go.
+    let mut row: Vec<usize> = children
+        .into_iter()
+        .flat_map(|children| children.iter())
+        .filter_map(|child| icons.get(*child).ok())
+        .map(|(_, icon, _)| key_types.order(&icon.color))
+        .collect();
+    for color in new_icons {
+        let key_type = key_types.get(&color);
+        let order = key_types.order(&color);
+        let appearing = IconAnimation::Appearing(Timer::from_seconds(APPEAR_SECONDS, TimerMode::Once));
+        let icon = match key_type.and_then(|t| t.key_icon.as_ref()) {
+            Some(icon) => {
+                let atlas = atlases
+                    .0
+                    .entry(icon.tileset_uid)
+                    .or_insert_with(|| texture_atlases.add(icon.atlas(asset_server.load(icon.rel_path.as_str()))))
+                    .clone();
+                commands.spawn((
+                    AtlasImageBundle {
+                        style: icon_style(&scale),
+                        texture_atlas: atlas,
+                        texture_atlas_image: UiTextureAtlasImage {
+                            index: icon.index,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    KeyIcon { color },
+                    appearing,
+                ))
+            },
+            // Key types without an icon are shown as a square in their
+            // LDtk color.
+            None => commands.spawn((
+                NodeBundle {
+                    style: icon_style(&scale),
+                    background_color: key_type.map_or(Color::WHITE, |t| t.tint).into(),
+                    ..default()
+                },
+                KeyIcon { color },
+                appearing,
+            )),
+        }
+        .id();
+        let position = row.partition_point(|shown| *shown <= order);
+        row.insert(position, order);
+        commands.entity(inventory).insert_children(position, &[icon]);
+    }
+}
+
+pub (crate) fn animate_icons(
+    time: Res<Time>,
+    mut icons: Query<(Entity, &mut Transform, &mut IconAnimation), With<KeyIcon>>,
+    mut commands: Commands,
+) {
+    for (entity, mut tf, mut animation) in icons.iter_mut() {
+        match animation.as_mut() {
+            IconAnimation::Appearing(timer) => {
+                timer.tick(time.delta());
+                // Overshoot, then settle at full size.
+                let t = timer.percent();
+                tf.scale = Vec3::splat(1. + 0.5 * (t * std::f32::consts::PI).sin());
+                if timer.finished() {
+                    tf.scale = Vec3::ONE;
+                    commands.entity(entity).remove::<IconAnimation>();
+                }
+            },
+            IconAnimation::Disappearing(timer) => {
+                timer.tick(time.delta());
+                tf.scale = Vec3::splat(timer.percent_left());
+                if timer.finished() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Where the bindings are read from on start and written to after a rebind.
+pub (crate) const BINDINGS_PATH: &str = "config/input.ron";
+
+/// How far a stick has to be pushed before it counts as pressed.
+pub (crate) const STICK_THRESHOLD: f32 = 0.5;
+
+/// Stick values below this are treated as 0, so that a resting stick that
+/// drifts slightly doesn't move the player.
+pub (crate) const STICK_DEADZONE: f32 = 0.15;
+
+/// Something the player can do, independent of the device used to do it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Interact,
+    Pause,
+    /// Rebind every action in turn, in the order of `Action::ALL`.
+    RebindControls,
+    /// Select save slot `0` to `3`.
+    SelectSlot(u8),
+    QuickSave,
+    QuickLoad,
+}
+
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Interact,
+        Self::Pause,
+        Self::RebindControls,
+        Self::SelectSlot(0),
+        Self::SelectSlot(1),
+        Self::SelectSlot(2),
+        Self::SelectSlot(3),
+        Self::QuickSave,
+        Self::QuickLoad,
+    ];
+}
+
+/// A single physical input. Gamepad bindings match every connected gamepad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    GamepadButton(GamepadButtonType),
+    /// One direction of a gamepad axis, e.g. the left stick pushed right.
+    GamepadAxis {
+        axis: GamepadAxisType,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    pub (crate) fn is_gamepad(&self) -> bool {
+        !matches!(self, Self::Key(_))
+    }
+}
+
+/// Maps every `Action` to the inputs that trigger it.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub (crate) bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use GamepadAxisType::*;
+        use GamepadButtonType::*;
+        let stick = |axis, positive| Binding::GamepadAxis { axis, positive };
+        let bindings = [
+            (Action::MoveUp, vec![
+                Binding::Key(KeyCode::W),
+                Binding::Key(KeyCode::Up),
+                Binding::GamepadButton(DPadUp),
+                stick(LeftStickY, true),
+            ]),
+            (Action::MoveDown, vec![
+                Binding::Key(KeyCode::S),
+                Binding::Key(KeyCode::Down),
+                Binding::GamepadButton(DPadDown),
+                stick(LeftStickY, false),
+            ]),
+            (Action::MoveLeft, vec![
+                Binding::Key(KeyCode::A),
+                Binding::Key(KeyCode::Left),
+                Binding::GamepadButton(DPadLeft),
+                stick(LeftStickX, false),
+            ]),
+            (Action::MoveRight, vec![
+                Binding::Key(KeyCode::D),
+                Binding::Key(KeyCode::Right),
+                Binding::GamepadButton(DPadRight),
+                stick(LeftStickX, true),
+            ]),
+            (Action::Interact, vec![
+                Binding::Key(KeyCode::E),
+                Binding::Key(KeyCode::Space),
+                Binding::GamepadButton(South),
+            ]),
+            (Action::Pause, vec![
+                Binding::Key(KeyCode::Escape),
+                Binding::GamepadButton(Start),
+            ]),
+            (Action::RebindControls, vec![
+                Binding::Key(KeyCode::F12),
+            ]),
+            (Action::SelectSlot(0), vec![Binding::Key(KeyCode::F1)]),
+            (Action::SelectSlot(1), vec![Binding::Key(KeyCode::F2)]),
+            (Action::SelectSlot(2), vec![Binding::Key(KeyCode::F3)]),
+            (Action::SelectSlot(3), vec![Binding::Key(KeyCode::F4)]),
+            (Action::QuickSave, vec![
+                Binding::Key(KeyCode::F5),
+                Binding::GamepadButton(LeftTrigger),
+            ]),
+            (Action::QuickLoad, vec![
+                Binding::Key(KeyCode::F9),
+                Binding::GamepadButton(RightTrigger),
+            ]),
+        ]
+        .into_iter()
+        .collect();
+        Self { bindings }
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Adds `binding` to `action`, removing it from any other action first.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.bindings.entry(action).or_default().push(binding);
+    }
+
+    /// Replaces the bindings of `action` on the same kind of device as
+    /// `binding` (keyboard or gamepad) with `binding`.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| b.is_gamepad() != binding.is_gamepad());
+        }
+        self.bind(action, binding);
+    }
+
+    /// Gives every action without bindings its default bindings, e.g. actions
+    /// added since `config/input.ron` was written.
+    pub (crate) fn fill_missing_from(&mut self, defaults: &InputBindings) {
+        for (action, bindings) in &defaults.bindings {
+            self.bindings.entry(*action).or_insert_with(|| bindings.clone());
+        }
+    }
+}
+
+/// What the player is doing this frame, after bindings are applied.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pub (crate) pressed: HashSet<Action>,
+    pub (crate) just_pressed: HashSet<Action>,
+    pub (crate) move_axis: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Combined movement input, with sticks contributing their analog value.
+    /// The length is at most 1.
+    pub fn move_axis(&self) -> Vec2 {
+        self.move_axis
+    }
+}
+
+/// Send this to bind the next key or gamepad button pressed to `0`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StartRebind(pub Action);
+
+/// The actions waiting for their next binding, in order.
+#[derive(Resource, Default, Debug)]
+pub (crate) struct PendingRebind(pub (crate) VecDeque<Action>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_only_replaces_bindings_of_the_same_device() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(Action::Interact, Binding::Key(KeyCode::F));
+
+        let interact = bindings.get(Action::Interact);
+        assert!(interact.contains(&Binding::Key(KeyCode::F)));
+        assert!(!interact.contains(&Binding::Key(KeyCode::E)));
+        assert!(interact.contains(&Binding::GamepadButton(GamepadButtonType::South)));
+    }
+
+    #[test]
+    fn binding_moves_key_away_from_its_old_action() {
+        let mut bindings = InputBindings::default();
+        bindings.bind(Action::Interact, Binding::Key(KeyCode::W));
+
+        assert!(!bindings.get(Action::MoveUp).contains(&Binding::Key(KeyCode::W)));
+        assert!(bindings.get(Action::Interact).contains(&Binding::Key(KeyCode::W)));
+    }
+
+    #[test]
+    fn bindings_round_trip_through_ron() {
+        let bindings = InputBindings::default();
+        let written = ron::to_string(&bindings).unwrap();
+        assert_eq!(ron::from_str::<InputBindings>(&written).unwrap(), bindings);
+    }
+
+    #[test]
+    fn missing_actions_get_their_default_bindings() {
+        let mut bindings = InputBindings::default();
+        bindings.bindings.remove(&Action::QuickSave);
+        bindings.bindings.insert(Action::Interact, vec![Binding::Key(KeyCode::F)]);
+        bindings.fill_missing_from(&InputBindings::default());
+
+        assert!(bindings.get(Action::QuickSave).contains(&Binding::Key(KeyCode::F5)));
+        assert_eq!(bindings.get(Action::Interact), [Binding::Key(KeyCode::F)]);
+    }
+}
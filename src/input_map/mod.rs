@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::input_map::plugin::InputMapPlugin;
+    pub use crate::input_map::components::{Action, ActionState, Binding, InputBindings, StartRebind};
+}
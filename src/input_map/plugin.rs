@@ -0,0 +1,39 @@
+// File: plugin.rs
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::input_map::{
+    components::*,
+    systems::*,
+};
+
+/// Turns keyboard and gamepad input into `ActionState` using the rebindable
+/// `InputBindings`. Bindings are read from `config/input.ron` on start and
+/// written back whenever an action is rebound through `StartRebind`, or
+/// through `Action::RebindControls` (F12), which rebinds every action in turn.
+#[derive(Default)]
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(InputBindings::default())
+            .insert_resource(ActionState::default())
+            .insert_resource(PendingRebind::default())
+            .add_event::<StartRebind>()
+            .add_systems(Startup, load_bindings)
+            .add_systems(
+                PreUpdate,
+                (
+                    capture_rebind,
+                    start_rebind,
+                    update_action_state,
+                    rebind_controls,
+                )
+                    .chain()
+                    .after(InputSystem)
+            )
+            ;
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::Path;
+
+use crate::input_map::components::*;
+
+pub (crate) fn load_bindings(mut commands: Commands) {
+    let path = Path::new(BINDINGS_PATH);
+    if !path.exists() {
+        info!("No {} found, using default bindings", BINDINGS_PATH);
+        return;
+    }
+    let loaded = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str::<InputBindings>(&contents).map_err(|e| e.to_string()));
+    match loaded {
+        Ok(mut bindings) => {
+            bindings.fill_missing_from(&InputBindings::default());
+            commands.insert_resource(bindings);
+        },
+        Err(e) => error!("Could not read {}, using default bindings: {}", BINDINGS_PATH, e),
+    }
+}
+
+fn write_bindings(bindings: &InputBindings) {
+    let path = Path::new(BINDINGS_PATH);
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            ron::ser::to_string_pretty(bindings, ron::ser::PrettyConfig::default())
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|contents| fs::write(path, contents).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        error!("Could not write {}: {}", BINDINGS_PATH, e);
+    }
+}
+
+/// Rescales a stick value so that the deadzone maps to 0 and the rest of the
+/// range still reaches 1.
+fn apply_deadzone(value: f32) -> f32 {
+    if value <= STICK_DEADZONE {
+        0.
+    } else {
+        ((value - STICK_DEADZONE) / (1. - STICK_DEADZONE)).min(1.)
+    }
+}
+
+/// How strongly `binding` is held, from 0 to 1. Keys and buttons are either
+/// 0 or 1; sticks report how far they are pushed in the bound direction,
+/// outside of the deadzone.
+fn binding_value(
+    binding: &Binding,
+    keys: &Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    gamepads: &Gamepads,
+) -> f32 {
+    match *binding {
+        Binding::Key(key) => {
+            if keys.pressed(key) { 1. } else { 0. }
+        },
+        Binding::GamepadButton(button_type) => {
+            let pressed = gamepads
+                .iter()
+                .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type)));
+            if pressed { 1. } else { 0. }
+        },
+        Binding::GamepadAxis { axis, positive } => gamepads
+            .iter()
+            .filter_map(|gamepad| axes.get(GamepadAxis::new(gamepad, axis)))
+            .map(|value| apply_deadzone(if positive { value } else { -value }))
+            .fold(0., f32::max),
+    }
+}
+
+pub (crate) fn update_action_state(
+    bindings: Res<InputBindings>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    pending: Res<PendingRebind>,
+    mut state: ResMut<ActionState>,
+) {
+    // Inputs pressed while rebinding are meant for the binding, not the game.
+    if !pending.0.is_empty() {
+        *state = ActionState::default();
+        return;
+    }
+
+    let action_value = |action: Action| {
+        bindings
+            .get(action)
+            .iter()
+            .map(|binding| binding_value(binding, &keys, &buttons, &axes, &gamepads))
+            .fold(0., f32::max)
+    };
+
+    let previously_pressed = std::mem::take(&mut state.pressed);
+    state.pressed = Action::ALL
+        .into_iter()
+        .filter(|action| action_value(*action) > STICK_THRESHOLD)
+        .collect();
+    state.just_pressed = state.pressed.difference(&previously_pressed).copied().collect();
+    state.move_axis = Vec2::new(
+        action_value(Action::MoveRight) - action_value(Action::MoveLeft),
+        action_value(Action::MoveUp) - action_value(Action::MoveDown),
+    )
+    .clamp_length_max(1.);
+}
+
+/// Walks through every action when `Action::RebindControls` is pressed.
+pub (crate) fn rebind_controls(
+    state: Res<ActionState>,
+    pending: Res<PendingRebind>,
+    mut rebind: EventWriter<StartRebind>,
+) {
+    if state.just_pressed(Action::RebindControls) && pending.0.is_empty() {
+        rebind.send_batch(Action::ALL.into_iter().map(StartRebind));
+    }
+}
+
+pub (crate) fn start_rebind(
+    mut events: EventReader<StartRebind>,
+    mut pending: ResMut<PendingRebind>,
+) {
+    for StartRebind(action) in events.iter() {
+        if pending.0.is_empty() {
+            info!("Press a key or button to bind to {:?}", action);
+        }
+        pending.0.push_back(*action);
+    }
+}
+
+pub (crate) fn capture_rebind(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut pending: ResMut<PendingRebind>,
+    mut bindings: ResMut<InputBindings>,
+) {
+    let Some(&action) = pending.0.front() else {
+        return;
+    };
+    let binding = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::GamepadButton(button.button_type))
+        });
+    let Some(binding) = binding else {
+        return;
+    };
+    bindings.rebind(action, binding);
+    pending.0.pop_front();
+    info!("Bound {:?} to {:?}", binding, action);
+    match pending.0.front() {
+        Some(next) => info!("Press a key or button to bind to {:?}", next),
+        None => write_bindings(&bindings),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_drops_drift_and_rescales_the_rest() {
+        assert_eq!(apply_deadzone(0.1), 0.);
+        assert_eq!(apply_deadzone(STICK_DEADZONE), 0.);
+        assert_eq!(apply_deadzone(1.), 1.);
+        let half = apply_deadzone((1. + STICK_DEADZONE) / 2.);
+        assert!((half - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+
+/// Anything the player can interact with by facing it and pressing interact.
+/// It is found by the grid cell its `GlobalTransform` is in.
+#[derive(Component, Default, Debug, Clone)]
+pub struct Interactable {
+    /// Shown to the player while this is the interaction target.
+    pub prompt: String,
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+        }
+    }
+}
+
+/// Set to true for the frame in which the player presses interact.
+#[derive(Resource, Default, PartialEq, Clone, Copy)]
+pub struct PlayerWantsToInteract(pub bool);
+
+/// The interactable the player would interact with right now, if any.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteractionTarget(pub Option<Entity>);
+
+/// Sent when the player interacts with `entity`. Systems that give an
+/// interactable its behaviour read this and check whether `entity` is theirs.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Interacted {
+    pub entity: Entity,
+}
+
+/// Marks the UI text that shows the prompt of the current target.
+#[derive(Component, Default)]
+pub (crate) struct InteractionPrompt;
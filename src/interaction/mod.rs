@@ -0,0 +1,13 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::interaction::plugin::{InteractionPlugin, InteractionSet};
+    pub use crate::interaction::components::{
+        Interactable,
+        Interacted,
+        InteractionTarget,
+        PlayerWantsToInteract,
+    };
+}
@@ -0,0 +1,45 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+use crate::interaction::{
+    components::*,
+    systems::*,
+};
+
+/// Finds the `Interactable` in front of the player and sends `Interacted`
+/// when the player presses interact. Systems that handle `Interacted` should
+/// run after `InteractionSet`.
+#[derive(Default)]
+pub struct InteractionPlugin<PlayerComponent: Component + Default> {
+    player: PhantomData<PlayerComponent>,
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InteractionSet;
+
+impl<PlayerComponent> Plugin for InteractionPlugin<PlayerComponent>
+where
+PlayerComponent: Component + Default,
+{
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PlayerWantsToInteract::default())
+            .insert_resource(InteractionTarget::default())
+            .add_event::<Interacted>()
+            .add_systems(Startup, spawn_prompt)
+            .add_systems(
+                Update,
+                (
+                    find_interaction_target::<PlayerComponent>,
+                    interact,
+                    update_prompt,
+                )
+                    .chain()
+                    .in_set(InteractionSet)
+                    .run_if(resource_exists::<crate::player_movement::prelude::PlayerFacing>())
+            )
+            ;
+    }
+}
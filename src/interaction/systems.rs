@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::interaction::components::*;
+use crate::player_movement::prelude::PlayerFacing;
+use crate::utils::translation_to_grid_coords;
+
+/// Picks the interactable in the cell the player is facing, or failing that
+/// the one in the cell the player is standing on.
+pub (crate) fn find_interaction_target<Player: Component>(
+    player: Query<&Transform, With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform), With<Interactable>>,
+    facing: Res<PlayerFacing>,
+    mut target: ResMut<InteractionTarget>,
+) {
+    let new_target = player.get_single().ok().and_then(|tf| {
+        let player_gc = translation_to_grid_coords(tf.translation.truncate());
+        let in_front = player_gc + GridCoords::from(facing.facing.offset());
+        // LDtk entities' `GridCoords` are relative to their level, so compare
+        // world positions instead.
+        let at = |cell: GridCoords| {
+            interactables
+                .iter()
+                .find(|(_, gtf)| {
+                    translation_to_grid_coords(gtf.translation().truncate()) == cell
+                })
+                .map(|(entity, _)| entity)
+        };
+        at(in_front).or_else(|| at(player_gc))
+    });
+    if target.0 != new_target {
+        target.0 = new_target;
+    }
+}
+
+pub (crate) fn interact(
+    wants_to_interact: Res<PlayerWantsToInteract>,
+    target: Res<InteractionTarget>,
+    mut interacted: EventWriter<Interacted>,
+) {
+    if !wants_to_interact.0 {
+        return;
+    }
+    if let Some(entity) = target.0 {
+        interacted.send(Interacted { entity });
+    }
+}
+
+pub (crate) fn spawn_prompt(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font_size: 16.,
+            color: Color::WHITE,
+            ..default()
+        })
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.),
+            left: Val::Px(8.),
+            ..default()
+        }),
+        InteractionPrompt,
+    ));
+}
+
+pub (crate) fn update_prompt(
+    target: Res<InteractionTarget>,
+    interactables: Query<&Interactable>,
+    mut prompt: Query<&mut Text, With<InteractionPrompt>>,
+) {
+    if !target.is_changed() {
+        return;
+    }
+    let Ok(mut text) = prompt.get_single_mut() else {
+        return;
+    };
+    text.sections[0].value = target
+        .0
+        .and_then(|entity| interactables.get(entity).ok())
+        .map_or_else(String::new, |interactable| format!("Interact: {}", interactable.prompt));
+}
@@ -1,13 +1,40 @@
 use bevy::prelude::*;
 use bevy::utils::{HashSet, HashMap};
 use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
+use crate::levels::int_grid::CellRule;
+use crate::levels::world_layout::LoadedLevel;
+use crate::player_movement::TerrainCost;
 
+
+/// Blocked and costly cells of every loaded level, in world grid
+/// coordinates.
 #[derive(Default, Resource)]
 pub struct Colliders {
     pub (crate) collider_locations: HashSet<GridCoords>,
-    level_width: i32,
-    level_height: i32,
+    /// Cells whose IntGrid rules change the player's speed or need an
+    /// ability to enter.
+    pub (crate) terrain_costs: HashMap<GridCoords, TerrainCost>,
+}
+
+impl Colliders {
+    /// Forgets the cells of a level that has despawned.
+    pub (crate) fn remove_level(&mut self, level: &LoadedLevel) {
+        self.collider_locations.retain(|gc| !level.contains(gc));
+        self.terrain_costs.retain(|gc, _| !level.contains(gc));
+    }
+}
+
+impl crate::player_movement::Collider for Colliders {
+    fn on_collider(&self, other: &GridCoords) -> bool {
+        self.collider_locations.contains(other)
+    }
+
+    fn terrain_cost(&self, cell: &GridCoords) -> Option<&TerrainCost> {
+        self.terrain_costs.get(cell)
+    }
 }
 
 // NExt level structs
@@ -17,21 +44,12 @@ pub (crate) struct NextLevel {
     pub (crate) entrance: String,
 }
 
-#[derive(Default, Resource)]
-pub (crate) struct NextLevels {
-    locations: HashSet<GridCoords>,
-}
 // Entrance structs
 #[derive(Default, Component, Debug)]
 pub (crate) struct Entrance {
     pub (crate) name: String,
 }
 
-#[derive(Default, Resource)]
-pub (crate) struct Entrances {
-    locations: HashMap<String, GridCoords>,
-}
-
 #[derive(Default, Bundle, LdtkEntity)]
 pub (crate) struct EntranceBundle {
     #[grid_coords]
@@ -40,16 +58,71 @@ pub (crate) struct EntranceBundle {
     entrance: Entrance,
 }
 
+/// IntGrid rules of every cell in the loaded levels that has any, in world
+/// grid coordinates. See `IntGridSemantics`.
+#[derive(Default, Resource, Debug)]
+pub struct Terrain {
+    pub (crate) cells: HashMap<GridCoords, CellRule>,
+}
 
-#[derive(Default, Component)]
-pub (crate) struct Unwalkable;
+impl Terrain {
+    pub fn at(&self, gc: &GridCoords) -> CellRule {
+        self.cells.get(gc).cloned().unwrap_or_default()
+    }
+
+    pub (crate) fn remove_level(&mut self, level: &LoadedLevel) {
+        self.cells.retain(|gc, _| !level.contains(gc));
+    }
+}
+
+/// Area in world space covered by the selected level, in pixels.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelBounds {
+    pub rect: Rect,
+}
+
+impl Default for LevelBounds {
+    fn default() -> Self {
+        Self {
+            rect: Rect::new(0., 0., 640., 480.),
+        }
+    }
+}
+
+/// Alpha of the player's sprite while they are hidden.
+pub (crate) const HIDDEN_ALPHA: f32 = 0.5;
 
+/// Added to the player while they stand in a cell that `hides_player`.
 #[derive(Default, Component)]
-pub (crate) struct Collider;
+pub struct HiddenInFoliage;
 
-#[derive(Default, Bundle, LdtkIntCell)]
-pub (crate) struct UnwalkablesBundle {
-    unwalkable: Unwalkable,
+/// While held, requested level switches wait instead of happening right away,
+/// e.g. until a screen transition has covered the screen.
+#[derive(Resource, Default, Debug)]
+pub struct LevelSwitchGate {
+    held: bool,
+}
+
+impl LevelSwitchGate {
+    pub fn hold(&mut self) {
+        self.held = true;
+    }
+
+    pub fn release(&mut self) {
+        self.held = false;
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+}
+
+/// The most recent switch that is waiting for the `LevelSwitchGate`.
+#[derive(Resource, Default, Debug)]
+pub (crate) struct PendingLevelSwitch {
+    pub (crate) to: String,
+    pub (crate) entrance: String,
+    pub (crate) pending: bool,
 }
 
 #[derive(Resource, Default, Debug)]
@@ -57,44 +130,88 @@ pub (crate) struct CameFrom {
     pub (crate) from: String,
 }
 
-#[derive(Default, Component, PartialEq, Eq, Hash, Debug, Clone, Copy)]
-pub enum LockKeyColor {
-    #[default]
-    Red,
-    Blue,
-    Green,
-    Yellow,
-    Brown
-}
-
-impl ToString for LockKeyColor {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Red => "red".to_string(),
-            Self::Blue => "blue".to_string(),
-            Self::Brown => "brown".to_string(),
-            Self::Green => "green".to_string(),
-            Self::Yellow => "yellow".to_string()
-        }
+/// The LDtk project the world is loaded from, relative to the assets folder.
+#[derive(Resource, Debug, Clone)]
+pub (crate) struct LevelProject(pub (crate) String);
+
+impl Default for LevelProject {
+    fn default() -> Self {
+        Self("farms.ldtk".to_string())
+    }
+}
+
+/// Iid of the selected level, once it has spawned.
+#[derive(Resource, Default, Debug)]
+pub (crate) struct CurrentLevel {
+    pub (crate) iid: String,
+}
+
+/// Marks LDtk entities whose removal should outlive the level. Once despawned
+/// through `LevelStates::despawn`, they stay gone when the level is spawned
+/// again.
+#[derive(Default, Component)]
+pub struct Persistent;
+
+/// What the player has changed in a single level.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelState {
+    /// Iids of `Persistent` entities that have been removed from the level.
+    pub (crate) despawned: HashSet<String>,
+}
+
+/// World state of every visited level, keyed by level iid. Applied whenever a
+/// level is spawned so the world remembers what the player has done.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelStates {
+    pub (crate) levels: HashMap<String, LevelState>,
+}
+
+impl LevelStates {
+    pub (crate) fn get(&self, level_iid: &str) -> Option<&LevelState> {
+        self.levels.get(level_iid)
+    }
+
+    /// Records that the entity with `entity_iid` in `level_iid` is gone for good.
+    pub (crate) fn record_despawned(&mut self, level_iid: &str, entity_iid: &EntityIid) {
+        self.levels
+            .entry(level_iid.to_string())
+            .or_default()
+            .despawned
+            .insert(entity_iid.as_str().to_string());
+    }
+}
+
+/// A key type, named after a value of the LDtk `LockColor` enum. Look it up in
+/// `KeyTypes` for its icons and color.
+#[derive(Default, Component, PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
+pub struct LockKeyColor(String);
+
+impl LockKeyColor {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// The LDtk enum value, e.g. `Red`.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for LockKeyColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_lowercase())
     }
 }
 
 impl From<&str> for LockKeyColor {
     fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "red" => Self::Red,
-            "blue" => Self::Blue,
-            "brown" => Self::Brown,
-            "green" => Self::Green,
-            "yellow" => Self::Yellow,
-            _ => panic!("Unknown color {}", s)
-        }
+        Self::new(s)
     }
 }
 
 impl From<String> for LockKeyColor {
     fn from(s: String) -> Self {
-        Self::from(s.as_str())
+        Self(s)
     }
 }
 
@@ -108,7 +225,64 @@ impl From<&String> for LockKeyColor {
 #[derive(Default, Component)]
 pub (crate)struct Key;
 
-#[derive(Default, Component)]
-pub (crate)struct Lock {
-    is_locked: bool,
+/// Grants `ability` (see `Abilities`) when interacted with, e.g. the boat
+/// that lets the player onto water.
+#[derive(Default, Component, Debug)]
+pub (crate) struct AbilityPickup {
+    pub (crate) ability: String,
+}
+
+/// A sign that shows `text` when interacted with.
+#[derive(Default, Component, Debug)]
+pub (crate) struct Sign {
+    pub (crate) text: String,
+}
+
+/// The sign whose text is on screen, if any. It is put away when the player
+/// stops facing it.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub (crate) struct ReadingSign(pub (crate) Option<Entity>);
+
+/// Marks the UI text that shows the sign being read.
+#[derive(Component, Default)]
+pub (crate) struct SignText;
+
+/// What it takes to open a lock.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Lock {
+    /// The color the lock is drawn in.
+    pub (crate) color: LockKeyColor,
+    /// How many keys of each color are needed.
+    pub (crate) required: HashMap<LockKeyColor, u32>,
+    /// Whether the keys are used up when the lock opens.
+    pub (crate) consumes_keys: bool,
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Self::single(LockKeyColor::default())
+    }
+}
+
+impl Lock {
+    /// A lock that takes one key of `color`, and uses it up.
+    pub (crate) fn single(color: LockKeyColor) -> Self {
+        Self {
+            required: [(color.clone(), 1)].into_iter().collect(),
+            color,
+            consumes_keys: true,
+        }
+    }
+
+    /// E.g. "2 red and 1 blue keys".
+    pub (crate) fn describe_requirement(&self) -> String {
+        let mut required: Vec<_> = self.required.iter().collect();
+        required.sort_by_key(|(color, _)| color.name());
+        let parts: Vec<String> = required
+            .into_iter()
+            .map(|(color, count)| format!("{} {}", count, color))
+            .collect();
+        let total: u32 = self.required.values().sum();
+        format!("{} {}", parts.join(" and "), if total == 1 { "key" } else { "keys" })
+    }
 }
@@ -0,0 +1,74 @@
+// File: events.rs
+//
+// Gameplay events sent by `LevelPlugin`. Within a frame they are sent in the
+// order of `GameplaySet`:
+//
+// 1. `PlayerEnteredLevel` (`GameplaySet::Enter`)
+// 2. `KeyPickedUp`, `LockOpened` (`GameplaySet::Interact`)
+// 3. `LevelSwitchRequested`, and `PlayerEnteredLevel` when walking into a
+//    neighbouring level (`GameplaySet::Switch`)
+//
+// A system that runs `.after(GameplaySet::Switch)` sees all of them in the
+// frame they are sent. Systems that run earlier still receive them, one frame
+// later. The switch itself happens in `GameplaySet::ApplySwitch`, unless the
+// `LevelSwitchGate` is held.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::levels::components::LockKeyColor;
+
+/// Sent when the player picks up a key.
+#[derive(Event, Debug, Clone)]
+pub struct KeyPickedUp {
+    pub color: LockKeyColor,
+}
+
+/// Sent when the player opens a lock.
+#[derive(Event, Debug, Clone)]
+pub struct LockOpened {
+    pub color: LockKeyColor,
+    /// Where the lock was, in world grid coordinates.
+    pub coords: GridCoords,
+}
+
+/// Sent when the player steps on a `SwitchLevel`. `LevelPlugin` switches
+/// `LevelSelection` in response, in the same frame unless the
+/// `LevelSwitchGate` is held.
+#[derive(Event, Debug, Clone)]
+pub struct LevelSwitchRequested {
+    /// Identifier of the level to switch to.
+    pub to: String,
+    /// Name of the `Entrance` in `to` that the player arrives at.
+    pub entrance: String,
+}
+
+/// Sent when a level has spawned and the player has been placed in it, or
+/// when the player walked into a neighbouring level of a seamless world.
+#[derive(Event, Debug, Clone)]
+pub struct PlayerEnteredLevel {
+    pub level_iid: String,
+    /// The entrance the player was placed at, or `None` if the player stayed
+    /// at the level's `PlayerSpawnPoint` or walked in.
+    pub entrance: Option<String>,
+    /// Whether the player walked in from a neighbouring level, which was
+    /// already spawned.
+    pub scrolled: bool,
+}
+
+/// The order `LevelPlugin` runs its systems in each frame.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameplaySet {
+    /// Caches colliders, goals, entrances, keys and locks on
+    /// `LevelEvent::Spawned`, and reapplies `LevelStates`.
+    Cache,
+    /// Places the player at the entrance they came through.
+    Enter,
+    /// Handles `Interacted` for keys, locks and signs.
+    Interact,
+    /// Checks whether the player stepped on a `SwitchLevel`, or walked into
+    /// a neighbouring level.
+    Switch,
+    /// Switches level, once the `LevelSwitchGate` is released.
+    ApplySwitch,
+}
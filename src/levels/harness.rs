@@ -0,0 +1,556 @@
+// File: harness.rs
+//
+// Headless test harness for `LevelPlugin`. Boots the plugin on top of
+// `MinimalPlugins`, without a window or GPU, loads `farms.ldtk` (or a test
+// project) from disk and lets tests script player input and step the app frame
+// by frame.
+
+use bevy::app::SubApp;
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_ldtk::prelude::*;
+use std::time::Duration;
+
+use crate::levels::{
+    components::*,
+    events::{GameplaySet, LevelSwitchRequested, PlayerEnteredLevel},
+    ldtk_entities::*,
+    plugin::LevelPlugin,
+    utils::*,
+    world_layout::{LoadedLevels, WorldPlayer},
+};
+use crate::interaction::prelude::*;
+use crate::player_movement::prelude::*;
+use crate::{Direction, Player};
+
+/// Simulated length of a single frame.
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// Upper bound on frames spent waiting for a project to load and spawn.
+const MAX_LOAD_FRAMES: usize = 10_000;
+
+/// Levels that have fired `LevelEvent::Spawned` since the harness started,
+/// in order.
+#[derive(Resource, Default, Debug)]
+struct SpawnedLevels(Vec<String>);
+
+fn record_spawned_levels(
+    mut level_events: EventReader<LevelEvent>,
+    mut spawned: ResMut<SpawnedLevels>,
+) {
+    for level_event in level_events.iter() {
+        if let LevelEvent::Spawned(level_iid) = level_event {
+            spawned.0.push(level_iid.clone());
+        }
+    }
+}
+
+/// Every `PlayerEnteredLevel` since the harness started, in order.
+#[derive(Resource, Default, Debug)]
+struct EnteredLevels(Vec<PlayerEnteredLevel>);
+
+fn record_entered_levels(
+    mut entered: EventReader<PlayerEnteredLevel>,
+    mut entered_levels: ResMut<EnteredLevels>,
+) {
+    entered_levels.0.extend(entered.iter().cloned());
+}
+
+/// Every `LevelSwitchRequested` since the harness started, in order.
+#[derive(Resource, Default, Debug)]
+struct SwitchRequests(Vec<LevelSwitchRequested>);
+
+fn record_switch_requests(
+    mut switch_requested: EventReader<LevelSwitchRequested>,
+    mut requests: ResMut<SwitchRequests>,
+) {
+    requests.0.extend(switch_requested.iter().cloned());
+}
+
+pub(crate) struct LevelHarness {
+    app: App,
+}
+
+impl LevelHarness {
+    /// Builds a headless app running `LevelPlugin`, `PlayerMover` and
+    /// `InteractionPlugin`, and steps it until `level` has been spawned.
+    pub(crate) fn new(level: LevelSelection) -> Self {
+        Self::with_project(LevelProject::default(), level)
+    }
+
+    /// Like `new`, but loads `project` instead of `farms.ldtk`.
+    pub(crate) fn with_project(project: LevelProject, level: LevelSelection) -> Self {
+        let mut app = App::new();
+        app
+            .add_plugins((
+                MinimalPlugins,
+                AssetPlugin::default(),
+                TransformPlugin,
+                HierarchyPlugin,
+                ImagePlugin::default_nearest(),
+            ))
+            .add_asset::<TextureAtlas>()
+            .add_asset::<Shader>()
+            .init_resource::<ClearColor>()
+            .add_plugins(PlayerMover::<Colliders>::default())
+            .add_plugins(InteractionPlugin::<Player>::default());
+        // The tilemap plugin that `LdtkPlugin` adds insists on a render app
+        // while it is built. Nothing is rendered, so it is taken away again
+        // before the plugins are finished.
+        app.insert_sub_app(RenderApp, SubApp::new(App::empty(), |_, _| {}));
+        app.insert_resource(project);
+        app.add_plugins(LevelPlugin::<Player>::default());
+        app.remove_sub_app(RenderApp);
+        app
+            .insert_resource(level)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .insert_resource(SpawnedLevels::default())
+            .insert_resource(EnteredLevels::default())
+            .insert_resource(SwitchRequests::default())
+            .add_systems(
+                Update,
+                (
+                    record_spawned_levels,
+                    record_entered_levels,
+                    record_switch_requests,
+                ).after(GameplaySet::ApplySwitch),
+            );
+        app.finish();
+        app.cleanup();
+
+        let mut harness = Self { app };
+        let loaded = harness.step_until(MAX_LOAD_FRAMES, |h| {
+            !h.spawned_levels().is_empty() && h.player_grid_coords().is_some()
+        });
+        assert!(loaded, "level never finished spawning");
+        harness
+    }
+
+    /// Runs a single frame.
+    pub(crate) fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Runs `frames` frames.
+    pub(crate) fn step_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Steps until `condition` holds or `max_frames` have run. Returns whether
+    /// the condition was met.
+    pub(crate) fn step_until(
+        &mut self,
+        max_frames: usize,
+        mut condition: impl FnMut(&mut Self) -> bool,
+    ) -> bool {
+        for _ in 0..max_frames {
+            self.step();
+            if condition(self) {
+                return true;
+            }
+            // Give the IO task pool a chance to finish loading assets.
+            std::thread::yield_now();
+        }
+        false
+    }
+
+    /// Holds the movement input in `direction`, the same way `take_input` does.
+    pub(crate) fn hold(&mut self, direction: Direction) {
+        self.app.world.resource_mut::<PlayerFacing>().facing = direction;
+        *self.app.world.resource_mut::<MovementInput>() = MovementInput(direction.offset().as_vec2());
+        *self.app.world.resource_mut::<PlayerWantsToMove>() = PlayerWantsToMove(true);
+    }
+
+    /// Releases all movement input.
+    pub(crate) fn release(&mut self) {
+        *self.app.world.resource_mut::<MovementInput>() = MovementInput::default();
+        *self.app.world.resource_mut::<PlayerWantsToMove>() = PlayerWantsToMove(false);
+    }
+
+    /// Faces `direction` and presses interact for a single frame.
+    pub(crate) fn interact(&mut self, direction: Direction) {
+        self.app.world.resource_mut::<PlayerFacing>().facing = direction;
+        *self.app.world.resource_mut::<PlayerWantsToInteract>() = PlayerWantsToInteract(true);
+        self.step();
+        *self.app.world.resource_mut::<PlayerWantsToInteract>() = PlayerWantsToInteract(false);
+        self.step();
+    }
+
+    /// Holds the `LevelSwitchGate`, the way a transition does while it covers
+    /// the screen.
+    pub(crate) fn hold_level_switches(&mut self) {
+        self.app.world.resource_mut::<LevelSwitchGate>().hold();
+    }
+
+    pub(crate) fn release_level_switches(&mut self) {
+        self.app.world.resource_mut::<LevelSwitchGate>().release();
+    }
+
+    pub(crate) fn give_key(&mut self, color: LockKeyColor) {
+        self.app.world.resource_mut::<CarriedKeys>().add(color);
+    }
+
+    pub(crate) fn lock_count(&mut self) -> usize {
+        let mut query = self.app.world.query_filtered::<(), With<Lock>>();
+        query.iter(&self.app.world).count()
+    }
+
+    pub(crate) fn set_movement_mode(&mut self, mode: MovementMode) {
+        self.app.insert_resource(mode);
+    }
+
+    pub(crate) fn player_translation(&mut self) -> Vec3 {
+        let mut query = self.app.world.query_filtered::<&Transform, With<Player>>();
+        query.single(&self.app.world).translation
+    }
+
+    /// Places the player in the centre of `gc`.
+    pub(crate) fn teleport_player(&mut self, gc: GridCoords) {
+        let mut query = self.app.world.query_filtered::<&mut Transform, With<Player>>();
+        let mut tf = query.single_mut(&mut self.app.world);
+        tf.translation = to_translation(gc, tf.translation.z);
+    }
+
+    pub(crate) fn player_grid_coords(&mut self) -> Option<GridCoords> {
+        let mut query = self.app.world.query_filtered::<&Transform, With<Player>>();
+        query.get_single(&self.app.world).ok().map(|tf| to_grid_coords(*tf))
+    }
+
+    /// Steps until the next `LevelEvent::Spawned`, plus one frame so that
+    /// commands issued in response to it have been applied.
+    pub(crate) fn wait_for_level_spawn(&mut self) -> bool {
+        let spawned = self.spawned_levels().len();
+        let loaded = self.step_until(MAX_LOAD_FRAMES, |h| h.spawned_levels().len() > spawned);
+        self.step();
+        loaded
+    }
+
+    pub(crate) fn player_count(&mut self) -> usize {
+        let mut query = self.app.world.query_filtered::<(), With<Player>>();
+        query.iter(&self.app.world).count()
+    }
+
+    /// Whether the player has been moved out of its level and into the world.
+    pub(crate) fn player_in_world(&mut self) -> bool {
+        let mut query = self.app.world.query_filtered::<(), (With<Player>, With<WorldPlayer>)>();
+        query.get_single(&self.app.world).is_ok()
+    }
+
+    pub(crate) fn player_hidden(&mut self) -> bool {
+        let mut query = self.app.world.query_filtered::<(), (With<Player>, With<HiddenInFoliage>)>();
+        query.get_single(&self.app.world).is_ok()
+    }
+
+    pub(crate) fn key_count(&mut self) -> usize {
+        let mut query = self.app.world.query_filtered::<(), With<Key>>();
+        query.iter(&self.app.world).count()
+    }
+
+    pub(crate) fn spawned_levels(&self) -> &[String] {
+        &self.app.world.resource::<SpawnedLevels>().0
+    }
+
+    pub(crate) fn entered_levels(&self) -> &[PlayerEnteredLevel] {
+        &self.app.world.resource::<EnteredLevels>().0
+    }
+
+    pub(crate) fn switch_requests(&self) -> &[LevelSwitchRequested] {
+        &self.app.world.resource::<SwitchRequests>().0
+    }
+
+    pub(crate) fn colliders(&self) -> &Colliders {
+        self.app.world.resource::<Colliders>()
+    }
+
+    pub(crate) fn carried_keys(&self) -> &CarriedKeys {
+        self.app.world.resource::<CarriedKeys>()
+    }
+
+    pub(crate) fn abilities(&self) -> &Abilities {
+        self.app.world.resource::<Abilities>()
+    }
+
+    pub(crate) fn came_from(&self) -> &CameFrom {
+        self.app.world.resource::<CameFrom>()
+    }
+
+    /// Identifier of the level the player is in, once it has spawned.
+    pub(crate) fn current_level(&self) -> Option<&str> {
+        let iid = &self.app.world.resource::<CurrentLevel>().iid;
+        self.app.world.resource::<LoadedLevels>().levels.get(iid).map(|level| level.identifier.as_str())
+    }
+
+    pub(crate) fn level_selection(&self) -> &LevelSelection {
+        self.app.world.resource::<LevelSelection>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::GRID_SIZE;
+
+    fn crossroads() -> LevelHarness {
+        LevelHarness::new(LevelSelection::Identifier("Crossroads".to_string()))
+    }
+
+    #[test]
+    fn colliders_are_cached_on_spawn() {
+        let harness = crossroads();
+        assert!(!harness.colliders().collider_locations.is_empty());
+    }
+
+    #[test]
+    fn interacting_with_key_picks_it_up() {
+        let mut harness = crossroads();
+        assert!(harness.carried_keys().is_empty());
+        harness.teleport_player(GridCoords::new(8, 11));
+        harness.step_frames(2);
+        assert!(harness.carried_keys().is_empty());
+
+        harness.interact(Direction::North);
+        assert_eq!(harness.carried_keys().count(&LockKeyColor::new("Blue")), 1);
+    }
+
+    #[test]
+    fn interacting_facing_away_from_key_does_nothing() {
+        let mut harness = crossroads();
+        harness.teleport_player(GridCoords::new(8, 11));
+        harness.interact(Direction::South);
+        assert!(harness.carried_keys().is_empty());
+    }
+
+    #[test]
+    fn stepping_on_switch_level_changes_level() {
+        let mut harness = crossroads();
+        harness.teleport_player(GridCoords::new(2, 7));
+        harness.step();
+        assert_eq!(
+            *harness.level_selection(),
+            LevelSelection::Identifier("Wheat_Fields".to_string())
+        );
+        assert_eq!(harness.came_from().from, "East");
+
+        assert!(harness.wait_for_level_spawn());
+        assert_eq!(harness.player_grid_coords(), Some(GridCoords::new(15, 6)));
+    }
+
+    #[test]
+    fn standing_on_a_held_switch_requests_it_once() {
+        let mut harness = crossroads();
+        harness.hold_level_switches();
+        harness.teleport_player(GridCoords::new(2, 7));
+        harness.step_frames(30);
+        assert_eq!(harness.switch_requests().len(), 1);
+        assert_eq!(
+            *harness.level_selection(),
+            LevelSelection::Identifier("Crossroads".to_string())
+        );
+
+        harness.release_level_switches();
+        assert!(harness.wait_for_level_spawn());
+        assert_eq!(harness.switch_requests().len(), 1);
+        assert_eq!(harness.came_from().from, "East");
+    }
+
+    #[test]
+    fn collected_key_stays_collected_after_leaving_level() {
+        let mut harness = crossroads();
+        assert_eq!(harness.key_count(), 1);
+        harness.teleport_player(GridCoords::new(8, 11));
+        harness.interact(Direction::North);
+        assert_eq!(harness.key_count(), 0);
+
+        harness.teleport_player(GridCoords::new(2, 7));
+        assert!(harness.wait_for_level_spawn());
+        harness.teleport_player(GridCoords::new(16, 6));
+        assert!(harness.wait_for_level_spawn());
+
+        assert_eq!(harness.key_count(), 0);
+        assert_eq!(harness.carried_keys().count(&LockKeyColor::new("Blue")), 1);
+    }
+
+    #[test]
+    fn stacked_locks_open_with_one_key() {
+        let mut harness = LevelHarness::new(LevelSelection::Identifier("Wheat_Fields".to_string()));
+        let lock = GridCoords::new(7, 4);
+        assert!(harness.colliders().collider_locations.contains(&lock));
+
+        harness.teleport_player(GridCoords::new(7, 5));
+        harness.interact(Direction::South);
+        assert_eq!(harness.lock_count(), 2);
+
+        // Two blue locks are stacked in the same cell, and neither uses up
+        // the key, so the cell stays blocked until both are open.
+        harness.give_key(LockKeyColor::new("Blue"));
+        harness.interact(Direction::South);
+        assert_eq!(harness.lock_count(), 1);
+        assert!(harness.colliders().collider_locations.contains(&lock));
+
+        harness.interact(Direction::South);
+        assert_eq!(harness.lock_count(), 0);
+        assert_eq!(harness.carried_keys().count(&LockKeyColor::new("Blue")), 1);
+        assert!(!harness.colliders().collider_locations.contains(&lock));
+    }
+
+    #[test]
+    fn wheat_hides_the_player() {
+        let mut harness = LevelHarness::new(LevelSelection::Identifier("Wheat_Fields".to_string()));
+        assert!(!harness.player_hidden());
+        harness.teleport_player(GridCoords::new(4, 6));
+        harness.step();
+        assert!(harness.player_hidden());
+        harness.teleport_player(GridCoords::new(4, 3));
+        harness.step();
+        assert!(!harness.player_hidden());
+    }
+
+    #[test]
+    fn water_can_only_be_entered_with_the_boat() {
+        let mut harness = crossroads();
+        // On the shore of the pond, next to the boat.
+        let shore = GridCoords::new(13, 4);
+        harness.teleport_player(shore);
+        harness.hold(Direction::South);
+        harness.step_frames(30);
+        harness.release();
+        assert_eq!(harness.player_grid_coords(), Some(shore));
+
+        harness.interact(Direction::West);
+        assert!(harness.abilities().has("Boat"));
+        harness.hold(Direction::South);
+        harness.step_frames(30);
+        harness.release();
+        assert!(harness.player_grid_coords().unwrap().y < shore.y);
+    }
+
+    #[test]
+    fn sand_slows_the_player_down() {
+        let mut harness = crossroads();
+        let distance_east_from = |harness: &mut LevelHarness, start| {
+            harness.teleport_player(start);
+            let from = harness.player_translation();
+            harness.hold(Direction::East);
+            harness.step_frames(10);
+            harness.release();
+            harness.player_translation().x - from.x
+        };
+        let on_grass = distance_east_from(&mut harness, GridCoords::new(2, 5));
+        let on_sand = distance_east_from(&mut harness, GridCoords::new(2, 3));
+        assert!(on_sand > 0.);
+        assert!((on_sand / on_grass - 0.8).abs() < 0.05);
+    }
+
+    #[test]
+    fn grid_step_stops_after_the_step_the_key_was_released_in() {
+        let mut harness = crossroads();
+        harness.set_movement_mode(MovementMode::GridStep);
+        let start = harness.player_grid_coords().unwrap();
+        harness.hold(Direction::East);
+        // A step takes 20 frames, let go halfway through it.
+        harness.step_frames(10);
+        harness.release();
+        harness.step_frames(60);
+
+        let end = harness.player_grid_coords().unwrap();
+        assert_eq!(end, start + GridCoords::new(1, 0));
+        assert_eq!(harness.player_translation(), to_translation(end, harness.player_translation().z));
+    }
+
+    #[test]
+    fn player_cannot_walk_through_colliders() {
+        let mut harness = crossroads();
+        let start = harness.player_grid_coords().unwrap();
+        harness.hold(Direction::North);
+        harness.step_frames(600);
+        harness.release();
+        let end = harness.player_grid_coords().unwrap();
+        assert!(!harness.colliders().collider_locations.contains(&end));
+        assert_eq!(start.x, end.x);
+    }
+
+    #[test]
+    fn player_hitbox_stops_flush_against_walls() {
+        let mut harness = crossroads();
+        // Just south of the fence west of the crossing.
+        harness.teleport_player(GridCoords::new(4, 7));
+        harness.hold(Direction::North);
+        harness.step_frames(600);
+        harness.release();
+        let (min, max) = PlayerHitbox::default().bounds_at(harness.player_translation().truncate());
+        assert!(!harness.colliders().blocks_area(min, max));
+        // The bottom edge of the fence in row 8.
+        let wall = 8. * GRID_SIZE as f32;
+        assert!((max.y - wall).abs() < 0.001, "stopped at {} instead of {}", max.y, wall);
+    }
+
+    /// Three 4x3 levels in a row: `West`, `Middle` and `East`, with their top
+    /// edge on the world origin so every cell has a negative y. West is left
+    /// of the origin and has the player and a fence in its top right corner.
+    fn gridvania() -> LevelHarness {
+        let mut harness = LevelHarness::with_project(
+            LevelProject("tests/gridvania.ldtk".to_string()),
+            LevelSelection::Identifier("West".to_string()),
+        );
+        // The layout is only known once the project has loaded, and levels
+        // spawned before that are respawned at their world position.
+        let adopted = harness.step_until(MAX_LOAD_FRAMES, |h| h.player_in_world());
+        assert!(adopted, "player never moved into the world");
+        harness
+    }
+
+    #[test]
+    fn gridvania_player_is_moved_into_the_world() {
+        let mut harness = gridvania();
+        harness.step_frames(2);
+        assert_eq!(harness.current_level(), Some("West"));
+        assert_eq!(harness.player_count(), 1);
+        assert!(harness.player_in_world());
+        assert_eq!(harness.player_grid_coords(), Some(GridCoords::new(-3, -2)));
+        assert!(harness.colliders().collider_locations.contains(&GridCoords::new(-1, -1)));
+    }
+
+    #[test]
+    fn gridvania_player_scrolls_into_neighbours() {
+        let mut harness = gridvania();
+        harness.step_frames(2);
+        let spawned = harness.spawned_levels().len();
+
+        harness.teleport_player(GridCoords::new(1, -2));
+        harness.step();
+        assert_eq!(harness.current_level(), Some("Middle"));
+        let entered = harness.entered_levels().last().unwrap();
+        assert!(entered.scrolled);
+        assert_eq!(entered.entrance, None);
+        assert_eq!(harness.player_grid_coords(), Some(GridCoords::new(1, -2)));
+
+        // East is spawned as Middle's neighbour, nothing is respawned and the
+        // player stays the same.
+        assert!(harness.wait_for_level_spawn());
+        assert_eq!(harness.spawned_levels().len(), spawned + 1);
+        assert_eq!(harness.player_count(), 1);
+        assert_eq!(harness.player_grid_coords(), Some(GridCoords::new(1, -2)));
+    }
+
+    #[test]
+    fn gridvania_colliders_go_with_despawned_levels() {
+        let mut harness = gridvania();
+        let fence = GridCoords::new(-1, -1);
+        harness.teleport_player(GridCoords::new(1, -2));
+        assert!(harness.wait_for_level_spawn());
+
+        // West is no longer a neighbour once the player is in East.
+        harness.teleport_player(GridCoords::new(5, -2));
+        let despawned = harness.step_until(MAX_LOAD_FRAMES, |h| {
+            !h.colliders().collider_locations.contains(&fence)
+        });
+        assert!(despawned);
+        assert_eq!(harness.current_level(), Some("East"));
+        assert_eq!(harness.player_count(), 1);
+        assert!(harness.player_in_world());
+        assert_eq!(harness.player_grid_coords(), Some(GridCoords::new(5, -2)));
+    }
+}
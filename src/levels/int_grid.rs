@@ -0,0 +1,166 @@
+// File: int_grid.rs
+//
+// Gameplay meaning of the IntGrid layers. Whether a cell blocks movement, slows
+// the player down, hides them or what their footsteps sound like is looked up
+// in `IntGridSemantics` by the layer and value identifiers set in LDtk, so
+// layers can be added or renamed without touching code.
+//
+// Like `validation`, this only depends on the LDtk data types so that the
+// level checking binaries can use it without starting the game.
+
+use bevy::prelude::{IVec2, Resource};
+use bevy_ecs_ldtk::ldtk::{LdtkJson, Level, Type};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+/// Overrides the default `IntGridSemantics` if it exists.
+pub const INT_GRID_SEMANTICS_PATH: &str = "assets/int_grid.ron";
+
+/// What the ground is made of, for footstep sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Surface {
+    Grass,
+    Sand,
+    Soil,
+    Water,
+}
+
+/// What a single IntGrid value means for the player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellRule {
+    /// The player can't walk onto the cell.
+    pub blocks: bool,
+    /// Multiplies the player's speed while on the cell.
+    pub speed: f32,
+    /// The player is hidden while on the cell.
+    pub hides_player: bool,
+    pub surface: Option<Surface>,
+    /// The player can only enter the cell with this ability, see
+    /// `Abilities`.
+    pub requires: Option<String>,
+}
+
+impl Default for CellRule {
+    fn default() -> Self {
+        Self {
+            blocks: false,
+            speed: 1.,
+            hides_player: false,
+            surface: None,
+            requires: None,
+        }
+    }
+}
+
+impl CellRule {
+    /// Combines the rules of every layer on a cell. Layers are visited from
+    /// the top, so the topmost surface is the one that is heard.
+    fn merge(&mut self, other: &CellRule) {
+        self.blocks |= other.blocks;
+        self.speed *= other.speed;
+        self.hides_player |= other.hides_player;
+        self.surface = self.surface.or(other.surface);
+        self.requires = self.requires.take().or_else(|| other.requires.clone());
+    }
+}
+
+/// Maps IntGrid layer identifiers to the rules of their values. Values are
+/// keyed by their identifier in LDtk, or by their number if they have none.
+/// Values that aren't listed have no effect.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntGridSemantics {
+    pub layers: HashMap<String, HashMap<String, CellRule>>,
+}
+
+impl Default for IntGridSemantics {
+    fn default() -> Self {
+        let blocks = CellRule {
+            blocks: true,
+            ..Default::default()
+        };
+        let ground = |surface, speed| CellRule {
+            surface: Some(surface),
+            speed,
+            ..Default::default()
+        };
+        // `Unwalkables` value `Invisibles` only marks where locks stand, the
+        // lock itself is the obstacle.
+        let layers = [
+            ("InvisibleWalls", vec![("Wall", blocks.clone())]),
+            ("Unwalkables", vec![("Fences", blocks.clone())]),
+            ("Foilage", vec![
+                ("Wheat", CellRule {
+                    speed: 0.7,
+                    hides_player: true,
+                    ..Default::default()
+                }),
+                ("TallGrass", CellRule {
+                    speed: 0.85,
+                    hides_player: true,
+                    ..Default::default()
+                }),
+            ]),
+            ("Ground", vec![
+                ("Grass", ground(Surface::Grass, 1.)),
+                ("Sand", ground(Surface::Sand, 0.8)),
+                ("Soil", ground(Surface::Soil, 1.)),
+                ("Water", CellRule {
+                    requires: Some("Boat".to_string()),
+                    ..ground(Surface::Water, 0.5)
+                }),
+            ]),
+        ];
+        Self {
+            layers: layers
+                .into_iter()
+                .map(|(layer, values)| {
+                    let values = values.into_iter().map(|(value, rule)| (value.to_string(), rule)).collect();
+                    (layer.to_string(), values)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl IntGridSemantics {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    /// Combined rules of every IntGrid cell in `level` that has any, keyed by
+    /// LDtk grid position (origin in the top left).
+    pub fn level_cells(&self, project: &LdtkJson, level: &Level) -> HashMap<IVec2, CellRule> {
+        let mut cells: HashMap<IVec2, CellRule> = HashMap::new();
+        let layers = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .filter(|layer| matches!(layer.layer_instance_type, Type::IntGrid));
+        for layer in layers {
+            let Some(rules) = self.layers.get(&layer.identifier) else {
+                continue;
+            };
+            let Some(definition) = project.defs.layers.iter().find(|d| d.uid == layer.layer_def_uid) else {
+                continue;
+            };
+            let value_rules: HashMap<i32, &CellRule> = definition
+                .int_grid_values
+                .iter()
+                .filter_map(|value| {
+                    let key = value.identifier.clone().unwrap_or_else(|| value.value.to_string());
+                    rules.get(&key).map(|rule| (value.value, rule))
+                })
+                .collect();
+            for (i, value) in layer.int_grid_csv.iter().enumerate() {
+                if let Some(rule) = value_rules.get(value) {
+                    let cell = IVec2::new(i as i32 % layer.c_wid, i as i32 / layer.c_wid);
+                    cells.entry(cell).or_default().merge(rule);
+                }
+            }
+        }
+        cells
+    }
+}
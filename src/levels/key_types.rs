@@ -0,0 +1,226 @@
+// File: key_types.rs
+//
+// Key types are read from the `LockColor` enum of the LDtk project, so that
+// designers can add new ones without touching code. Icons come from the enum
+// value's tile, or failing that from the enum tags of the tileset that uses
+// `LockColor` as its tag source: the first tagged tile is the key, the second
+// the lock. Each icon remembers its tileset, so the HUD can draw it from
+// whichever image it is in.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::{EnumDefinition, LdtkJson, TilesetDefinition};
+use bevy_ecs_ldtk::prelude::*;
+use std::fmt;
+
+use crate::levels::components::LockKeyColor;
+use crate::levels::validation::KEY_TYPE_ENUM;
+
+/// A tile of an LDtk tileset, used as an icon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileIcon {
+    pub tileset_uid: i32,
+    /// The tileset image, relative to the LDtk project. The project sits at
+    /// the root of the assets folder, so this is also its asset path.
+    pub rel_path: String,
+    /// Index of the tile in the atlas built by `atlas`.
+    pub index: usize,
+    tile_size: f32,
+    columns: usize,
+    rows: usize,
+    padding: f32,
+    spacing: f32,
+}
+
+impl TileIcon {
+    /// `None` if the tileset has no image, like the internal LDtk icons.
+    fn new(tileset: &TilesetDefinition, index: usize) -> Option<Self> {
+        Some(Self {
+            tileset_uid: tileset.uid,
+            rel_path: tileset.rel_path.clone()?,
+            index,
+            tile_size: tileset.tile_grid_size as f32,
+            columns: tileset.c_wid.max(0) as usize,
+            rows: tileset.c_hei.max(0) as usize,
+            padding: tileset.padding as f32,
+            spacing: tileset.spacing as f32,
+        })
+    }
+
+    /// The atlas of the whole tileset, with `image` loaded from `rel_path`.
+    pub fn atlas(&self, image: Handle<Image>) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            image,
+            Vec2::splat(self.tile_size),
+            self.columns,
+            self.rows,
+            Some(Vec2::splat(self.spacing)),
+            Some(Vec2::splat(self.padding)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyType {
+    pub color: LockKeyColor,
+    /// The enum value's color in LDtk.
+    pub tint: Color,
+    pub key_icon: Option<TileIcon>,
+    pub lock_icon: Option<TileIcon>,
+}
+
+/// Every key type in the project, in the order of the LDtk enum.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct KeyTypes {
+    types: Vec<KeyType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyTypeError {
+    /// The project has no `LockColor` enum.
+    MissingEnum,
+}
+
+impl fmt::Display for KeyTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingEnum => write!(
+                f,
+                "the LDtk project has no `{}` enum, so there are no key types",
+                KEY_TYPE_ENUM
+            ),
+        }
+    }
+}
+
+fn tint_from_ldtk(color: i32) -> Color {
+    Color::rgb_u8((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
+
+fn tile_index(tileset: &TilesetDefinition, x: i32, y: i32) -> usize {
+    let grid = tileset.tile_grid_size.max(1);
+    ((y / grid) * tileset.c_wid + x / grid) as usize
+}
+
+impl KeyTypes {
+    pub fn from_project(project: &LdtkJson) -> Result<Self, KeyTypeError> {
+        let definition: &EnumDefinition = project
+            .defs
+            .enums
+            .iter()
+            .find(|e| e.identifier == KEY_TYPE_ENUM)
+            .ok_or(KeyTypeError::MissingEnum)?;
+        let tileset = |uid: i32| project.defs.tilesets.iter().find(|t| t.uid == uid);
+        let tagged_tileset = project
+            .defs
+            .tilesets
+            .iter()
+            .find(|t| t.tags_source_enum_uid == Some(definition.uid));
+
+        let types = definition
+            .values
+            .iter()
+            .map(|value| {
+                let from_rect = value.tile_rect.as_ref().and_then(|rect| {
+                    let t = tileset(rect.tileset_uid)?;
+                    TileIcon::new(t, tile_index(t, rect.x, rect.y))
+                });
+                let tagged: Vec<TileIcon> = tagged_tileset
+                    .and_then(|t| {
+                        let tag = t.enum_tags.iter().find(|tag| tag.enum_value_id == value.id)?;
+                        Some(tag.tile_ids.iter().filter_map(|id| TileIcon::new(t, *id as usize)).collect())
+                    })
+                    .unwrap_or_default();
+                KeyType {
+                    color: LockKeyColor::new(value.id.clone()),
+                    tint: tint_from_ldtk(value.color),
+                    key_icon: from_rect.or_else(|| tagged.first().cloned()),
+                    lock_icon: tagged.get(1).cloned(),
+                }
+            })
+            .collect();
+        Ok(Self { types })
+    }
+
+    pub fn get(&self, color: &LockKeyColor) -> Option<&KeyType> {
+        self.types.iter().find(|t| t.color == *color)
+    }
+
+    /// Position of `color` in the LDtk enum, for sorting. Unknown colors sort
+    /// last.
+    pub fn order(&self, color: &LockKeyColor) -> usize {
+        self.types
+            .iter()
+            .position(|t| t.color == *color)
+            .unwrap_or(self.types.len())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &KeyType> {
+        self.types.iter()
+    }
+
+}
+
+/// Rebuilds `KeyTypes` whenever the LDtk project is loaded or hot-reloaded.
+/// Keys and locks with an invalid type are reported by `report_project_problems`.
+pub(crate) fn load_key_types(
+    mut asset_events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut key_types: ResMut<KeyTypes>,
+) {
+    for event in asset_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(ldtk_asset) = ldtk_assets.get(handle) else {
+            continue;
+        };
+        match KeyTypes::from_project(&ldtk_asset.project) {
+            Ok(types) => {
+                info!("Loaded {} key types", types.types.len());
+                *key_types = types;
+            },
+            Err(error) => error!("Could not load key types: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farms() -> LdtkJson {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/farms.ldtk");
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn key_types_come_from_the_lock_color_enum() {
+        let project = farms();
+        let types = KeyTypes::from_project(&project).unwrap();
+
+        let tiles = |key_type: &KeyType| {
+            (
+                key_type.key_icon.as_ref().map(|icon| icon.index),
+                key_type.lock_icon.as_ref().map(|icon| icon.index),
+            )
+        };
+        let blue = types.get(&LockKeyColor::new("Blue")).unwrap();
+        assert_eq!(tiles(blue), (Some(0), Some(5)));
+        let red = types.get(&LockKeyColor::new("Red")).unwrap();
+        assert_eq!(tiles(red), (Some(1), Some(6)));
+
+        let icon = blue.key_icon.as_ref().unwrap();
+        assert_eq!(icon.rel_path, "keys_locks.png");
+        let atlas = icon.atlas(Handle::default());
+        assert_eq!(atlas.textures[icon.index], Rect::new(0., 0., 16., 16.));
+        assert_eq!(atlas.len(), 10);
+        assert!(types.get(&LockKeyColor::new("Purple")).is_none());
+    }
+
+    #[test]
+    fn missing_key_type_enum_is_an_error_instead_of_a_panic() {
+        let mut project = farms();
+        project.defs.enums.retain(|e| e.identifier != KEY_TYPE_ENUM);
+        assert_eq!(KeyTypes::from_project(&project), Err(KeyTypeError::MissingEnum));
+    }
+}
@@ -1,7 +1,11 @@
 use crate::levels::components::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
-use bevy::utils::{HashSet, HashMap};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::interaction::prelude::Interactable;
+use crate::levels::validation::KEY_TYPE_FIELD;
 
 
 #[derive(Default, Bundle, LdtkEntity)]
@@ -13,6 +17,9 @@ pub (crate) struct KeyBundle {
     #[ldtk_entity]
     color: LockKeyColor,
     key: Key,
+    persistent: Persistent,
+    #[with(key_interactable)]
+    interactable: Interactable,
 }
 
 #[derive(Default, Bundle, LdtkEntity)]
@@ -23,22 +30,168 @@ pub (crate) struct LockBundle {
     grid_coords: GridCoords,
     #[ldtk_entity]
     color: LockKeyColor,
+    #[ldtk_entity]
     lock: Lock,
+    persistent: Persistent,
+    #[with(lock_interactable)]
+    interactable: Interactable,
+}
+
+fn key_interactable(entity_instance: &EntityInstance) -> Interactable {
+    Interactable::new(format!("Pick up {} key", LockKeyColor::bundle_color(entity_instance)))
+}
+
+fn lock_interactable(entity_instance: &EntityInstance) -> Interactable {
+    let lock = Lock::from_instance(entity_instance);
+    Interactable::new(format!("Unlock (needs {})", lock.describe_requirement()))
+}
+
+impl Lock {
+    /// Reads the lock rules from the LDtk entity. Besides `LockColor`, a lock
+    /// may have these optional fields:
+    ///
+    /// * `KeyCount` (Int): how many `LockColor` keys are needed. Defaults to 1.
+    /// * `ExtraKeys` (Array of LockColor): one more key of each listed color.
+    /// * `ConsumesKeys` (Bool): whether the keys are used up. Defaults to true.
+    fn from_instance(entity_instance: &EntityInstance) -> Self {
+        let color = LockKeyColor::bundle_color(entity_instance);
+        let mut lock = Self::single(color.clone());
+        if let Ok(count) = entity_instance.get_int_field("KeyCount") {
+            lock.required.insert(color.clone(), (*count).max(1) as u32);
+        }
+        if let Ok(extra_keys) = entity_instance.iter_enums_field("ExtraKeys") {
+            for extra in extra_keys {
+                *lock.required.entry(LockKeyColor::from(extra)).or_default() += 1;
+            }
+        }
+        if let Ok(consumes_keys) = entity_instance.get_bool_field("ConsumesKeys") {
+            lock.consumes_keys = *consumes_keys;
+        }
+        lock
+    }
+}
+
+impl LdtkEntity for Lock {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self::from_instance(entity_instance)
+    }
+}
+
+impl LdtkEntity for AbilityPickup {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let ability = entity_instance
+            .get_string_field("ability")
+            .cloned()
+            .unwrap_or_default();
+        Self {
+            ability
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub (crate) struct AbilityBundle {
+    #[sprite_sheet_bundle(no_grid)]
+    sprite_sheet: SpriteSheetBundle,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[ldtk_entity]
+    pickup: AbilityPickup,
+    persistent: Persistent,
+    #[with(ability_interactable)]
+    interactable: Interactable,
 }
 
-#[derive(Default, Resource, Debug)]
-pub(crate) struct Keys {
-    color_location_pairing: HashMap<LockKeyColor, GridCoords>,
+fn ability_interactable(entity_instance: &EntityInstance) -> Interactable {
+    let ability = entity_instance.get_string_field("ability").cloned().unwrap_or_default();
+    Interactable::new(format!("Take the {}", ability.to_lowercase()))
 }
 
-#[derive(Default, Resource, Debug)]
-pub(crate) struct Locks {
-    color_location_pairing: HashMap<LockKeyColor, GridCoords>,
+impl LdtkEntity for Sign {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let text = entity_instance
+            .get_string_field("text")
+            .cloned()
+            .unwrap_or_default();
+        Self {
+            text
+        }
+    }
 }
 
-#[derive(Default, Resource, Debug)]
+#[derive(Default, Bundle, LdtkEntity)]
+pub (crate) struct SignBundle {
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[ldtk_entity]
+    sign: Sign,
+    #[with(sign_interactable)]
+    interactable: Interactable,
+}
+
+fn sign_interactable(_: &EntityInstance) -> Interactable {
+    Interactable::new("Read sign")
+}
+
+/// Keys the player is carrying, counted per color.
+#[derive(Default, Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CarriedKeys {
-    pub keys: HashSet<LockKeyColor>,
+    pub keys: HashMap<LockKeyColor, u32>,
+}
+
+impl CarriedKeys {
+    pub fn add(&mut self, color: LockKeyColor) {
+        *self.keys.entry(color).or_default() += 1;
+    }
+
+    pub fn count(&self, color: &LockKeyColor) -> u32 {
+        self.keys.get(color).copied().unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn can_open(&self, lock: &Lock) -> bool {
+        lock.required
+            .iter()
+            .all(|(color, needed)| self.count(color) >= *needed)
+    }
+
+    /// Uses up the keys `lock` needs. Does nothing unless `can_open` holds.
+    pub fn spend(&mut self, lock: &Lock) {
+        if !self.can_open(lock) {
+            return;
+        }
+        for (color, needed) in lock.required.iter() {
+            let carried = self.keys.entry(color.clone()).or_default();
+            *carried -= needed;
+            if *carried == 0 {
+                self.keys.remove(color);
+            }
+        }
+    }
 }
 
 impl LdtkEntity for Entrance {
@@ -50,10 +203,15 @@ impl LdtkEntity for Entrance {
         _asset_server: &AssetServer,
         _texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
+        // Missing fields are reported by `validate_project` when the project
+        // is loaded.
         let name = entity_instance
             .get_enum_field("name")
-            .expect("Should have a name")
-            .clone();
+            .cloned()
+            .unwrap_or_else(|e| {
+                warn!("Entrance {} has no name: {}", entity_instance.iid, e);
+                String::new()
+            });
         Self {
             name
         }
@@ -69,15 +227,18 @@ impl LdtkEntity for NextLevel {
         _asset_server: &AssetServer,
         _texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
-        let next_level = entity_instance
-            .get_enum_field("to_level")
-            .expect("to_level should exist");
-        let entrance = entity_instance
-            .get_enum_field("entrance")
-            .expect("entrance should exist");
+        let field = |name: &str| {
+            entity_instance
+                .get_enum_field(name)
+                .cloned()
+                .unwrap_or_else(|e| {
+                    warn!("SwitchLevel {} has no {}: {}", entity_instance.iid, name, e);
+                    String::new()
+                })
+        };
         let s = Self {
-            next_level: (*next_level).clone(),
-            entrance: (*entrance).clone(),
+            next_level: field("to_level"),
+            entrance: field("entrance"),
         };
         debug!("Making NextLevel with next_level = {:?}", s);
         s
@@ -93,6 +254,21 @@ pub (crate) struct NextLevelBundle {
     next_level: NextLevel,
 }
 
+impl LockKeyColor {
+    /// Reads the key type of a `Key` or `Lock`. A missing value is reported
+    /// when the project is loaded (see `report_project_problems`), so it is
+    /// only logged here.
+    fn bundle_color(entity_instance: &EntityInstance) -> Self {
+        match entity_instance.get_enum_field(KEY_TYPE_FIELD) {
+            Ok(color) => color.into(),
+            Err(e) => {
+                warn!("{} {} has no key type: {}", entity_instance.identifier, entity_instance.iid, e);
+                Self::default()
+            },
+        }
+    }
+}
+
 impl LdtkEntity for LockKeyColor {
     fn bundle_entity(
         entity_instance: &EntityInstance,
@@ -102,19 +278,39 @@ impl LdtkEntity for LockKeyColor {
         _asset_server: &AssetServer,
         _texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
-        entity_instance
-            .get_enum_field("LockColor")
-            .expect("color should exist")
-            .into()
+        Self::bundle_color(entity_instance)
     }
 }
 
 #[derive(Default, Bundle, LdtkEntity)]
-pub struct PlayerBundle<PlayerComponent, AnimationTimer> where
-PlayerComponent: Component + Default,
-AnimationTimer: Resource + Component + Default, {
+pub struct PlayerBundle<PlayerComponent> where
+PlayerComponent: Component + Default, {
     player: PlayerComponent,
     #[sprite_sheet_bundle]
     sprite_sheet_bundle: SpriteSheetBundle,
-    timer: AnimationTimer,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consuming_lock_spends_every_required_key() {
+        let mut lock = Lock::single(LockKeyColor::new("Red"));
+        lock.required.insert(LockKeyColor::new("Red"), 2);
+        lock.required.insert(LockKeyColor::new("Blue"), 1);
+
+        let mut carried = CarriedKeys::default();
+        carried.add(LockKeyColor::new("Red"));
+        carried.add(LockKeyColor::new("Blue"));
+        assert!(!carried.can_open(&lock));
+
+        carried.add(LockKeyColor::new("Red"));
+        carried.add(LockKeyColor::new("Red"));
+        assert!(carried.can_open(&lock));
+
+        carried.spend(&lock);
+        assert_eq!(carried.count(&LockKeyColor::new("Red")), 1);
+        assert_eq!(carried.count(&LockKeyColor::new("Blue")), 0);
+        assert!(!carried.keys.contains_key(&LockKeyColor::new("Blue")));
+    }
+}
@@ -1,9 +1,41 @@
 mod components;
+mod events;
+mod int_grid;
+mod key_types;
 mod systems;
 mod plugin;
 mod ldtk_entities;
 mod utils;
+mod validation;
+mod world_layout;
+mod solver;
+#[cfg(test)]
+pub(crate) mod harness;
 
 pub mod prelude {
     pub use crate::levels::plugin::LevelPlugin;
+    pub use crate::levels::components::{
+        Colliders,
+        HiddenInFoliage,
+        LevelBounds,
+        LevelStates,
+        LevelSwitchGate,
+        LockKeyColor,
+        Persistent,
+        Terrain,
+    };
+    pub use crate::levels::int_grid::{CellRule, IntGridSemantics, Surface, INT_GRID_SEMANTICS_PATH};
+    pub use crate::levels::ldtk_entities::CarriedKeys;
+    pub use crate::levels::key_types::{KeyType, KeyTypeError, KeyTypes};
+    pub use crate::levels::validation::{validate_project, ValidationError};
+    pub use crate::levels::solver::{solve, SolveError, SolveReport, Step, GOAL_LEVEL, MAX_STATES};
+    pub use crate::levels::world_layout::LevelLayout;
+    pub use crate::levels::events::{
+        GameplaySet,
+        KeyPickedUp,
+        LevelSwitchRequested,
+        LockOpened,
+        PlayerEnteredLevel,
+    };
+    pub(crate) use crate::levels::components::{CameFrom, CurrentLevel};
 }
\ No newline at end of file
@@ -4,56 +4,107 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use std::marker::PhantomData;
 
+use crate::interaction::prelude::*;
 use crate::levels::{
     components::*,
+    events::*,
+    int_grid::IntGridSemantics,
+    key_types::*,
     systems::*,
     ldtk_entities::*,
+    validation::START_LEVEL_INDEX,
+    world_layout::*,
 };
 
 #[derive(Default)]
-pub struct LevelPlugin<PlayerComponent, AnimationTimer> where
-PlayerComponent: Component + Default,
-AnimationTimer: Resource + Component + Default {
+pub struct LevelPlugin<PlayerComponent> where
+PlayerComponent: Component + Default {
     player_bundle: PhantomData<PlayerComponent>,
-    animation_timer: PhantomData<AnimationTimer>,
 }
 
-impl<PlayerComponent, AnimationTimer> Plugin for LevelPlugin<PlayerComponent, AnimationTimer>
+impl<PlayerComponent> Plugin for LevelPlugin<PlayerComponent>
 where
 PlayerComponent: Component + Default,
-AnimationTimer: Resource + Component + Default,
 {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(LdtkPlugin)
-            .insert_resource(LevelSelection::Index(1))
+            .insert_resource(LevelSelection::Index(START_LEVEL_INDEX))
             .insert_resource(Colliders::default())
-            .insert_resource(NextLevels::default())
-            .insert_resource(Entrances::default())
             .insert_resource(CameFrom::default())
-            .insert_resource(Keys::default())
-            .insert_resource(Locks::default())
+            .insert_resource(LevelSwitchGate::default())
+            .insert_resource(PendingLevelSwitch::default())
             .insert_resource(CarriedKeys::default())
-            .register_ldtk_entity::<PlayerBundle::<PlayerComponent, AnimationTimer>>("PlayerSpawnPoint")
+            .insert_resource(CurrentLevel::default())
+            .insert_resource(LevelStates::default())
+            .insert_resource(KeyTypes::default())
+            .insert_resource(IntGridSemantics::default())
+            .insert_resource(Terrain::default())
+            .insert_resource(LevelBounds::default())
+            .insert_resource(LevelLayout::default())
+            .insert_resource(LoadedLevels::default())
+            .insert_resource(ReadingSign::default())
+            .init_resource::<LevelProject>()
+            .add_event::<KeyPickedUp>()
+            .add_event::<LockOpened>()
+            .add_event::<LevelSwitchRequested>()
+            .add_event::<PlayerEnteredLevel>()
+            .register_ldtk_entity::<PlayerBundle::<PlayerComponent>>("PlayerSpawnPoint")
             .register_ldtk_entity::<NextLevelBundle>("SwitchLevel")
             .register_ldtk_entity::<EntranceBundle>("Entrance")
             .register_ldtk_entity::<KeyBundle>("Key")
             .register_ldtk_entity::<LockBundle>("Lock")
-            .register_ldtk_int_cell_for_layer::<UnwalkablesBundle>("Unwalkables", 1)
+            .register_ldtk_entity::<SignBundle>("Sign")
+            .register_ldtk_entity::<AbilityBundle>("Ability")
+            .configure_sets(
+                Update,
+                (
+                    GameplaySet::Cache,
+                    GameplaySet::Enter,
+                    GameplaySet::Interact,
+                    GameplaySet::Switch,
+                    GameplaySet::ApplySwitch,
+                ).chain()
+            )
+            .configure_set(Update, GameplaySet::Interact.after(InteractionSet))
             .add_systems(
                 Update,
                 (
-                    cache_collider_location,
-                    cache_goal_location,
-                    cache_entrance_location,
-                    cache_locks_and_keys,
-                    check_for_goals::<PlayerComponent>,
-                    move_player_to_entrance::<PlayerComponent>,
-                    pickup_key::<PlayerComponent>,
-                    is_near_lock::<PlayerComponent>,
+                    (
+                        load_key_types,
+                        report_project_problems,
+                        apply_world_layout,
+                        track_loaded_levels,
+                        (
+                            cache_collider_location,
+                            track_current_level,
+                        ).after(track_loaded_levels),
+                        adopt_player::<PlayerComponent>.after(track_current_level),
+                        restore_level_state.after(cache_collider_location),
+                    ).in_set(GameplaySet::Cache),
+                    // The player has to be in the world before it is moved.
+                    apply_deferred
+                        .after(GameplaySet::Cache)
+                        .before(GameplaySet::Enter),
+                    (
+                        move_player_to_entrance::<PlayerComponent>,
+                        hide_player_in_foliage::<PlayerComponent>,
+                    ).chain().in_set(GameplaySet::Enter),
+                    (
+                        pickup_key,
+                        pickup_ability,
+                        open_lock,
+                        read_sign,
+                    ).in_set(GameplaySet::Interact),
+                    (
+                        check_for_goals::<PlayerComponent>,
+                        queue_level_switch,
+                        scroll_to_neighbour::<PlayerComponent>,
+                    ).chain().in_set(GameplaySet::Switch),
+                    switch_level.in_set(GameplaySet::ApplySwitch),
                 )
             )
-            .add_systems(Startup, setup)
+            .add_systems(Startup, (load_int_grid_semantics, setup, spawn_sign_text))
             ;
     }
 }
\ No newline at end of file
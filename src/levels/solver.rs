@@ -0,0 +1,518 @@
+// File: solver.rs
+//
+// Offline check that the key and lock puzzles can be finished. The LDtk
+// project is turned into a graph: cells that `IntGridSemantics` says block
+// and cells with closed locks can't be walked on, `SwitchLevel`s
+// lead to the matching `Entrance` of their level, and keys are picked up and
+// locks opened from a neighbouring cell, like the interact action does.
+//
+// The search runs over puzzle states: where the player is, which keys have
+// been picked up and which locks have been opened. It finds the shortest
+// sequence of pick-ups and unlocks that gets the player into `GOAL_LEVEL`,
+// and every step that turns a winnable game into one that can't be won.
+//
+// Like `validation`, this only depends on the LDtk data types so that the
+// `solve-levels` binary can use it without starting the game.
+
+use bevy::prelude::IVec2;
+use bevy_ecs_ldtk::ldtk::{LdtkJson, Level};
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::levels::int_grid::IntGridSemantics;
+use crate::levels::validation::{entities, lock_requirements, KEY_TYPE_FIELD, START_LEVEL_INDEX};
+
+/// The level that counts as beating the game once the player enters it.
+pub const GOAL_LEVEL: &str = "Finale";
+
+/// Upper bound on puzzle states, so that a huge project can't hang the check.
+pub const MAX_STATES: usize = 100_000;
+
+const NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum SolveError {
+    MissingStartLevel,
+    MissingGoalLevel,
+    MissingSpawnPoint {
+        level: String,
+    },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStartLevel => write!(f, "there is no level at index {} to start in", START_LEVEL_INDEX),
+            Self::MissingGoalLevel => write!(f, "there is no {} level to reach", GOAL_LEVEL),
+            Self::MissingSpawnPoint { level } => write!(f, "the start level {} has no PlayerSpawnPoint", level),
+        }
+    }
+}
+
+/// A single thing the player has to do on the way to the goal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Step {
+    PickUpKey {
+        level: String,
+        iid: String,
+        color: String,
+    },
+    OpenLock {
+        level: String,
+        iid: String,
+        colors: Vec<String>,
+    },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PickUpKey { level, iid, color } => {
+                write!(f, "pick up the {} key {} in {}", color, iid, level)
+            },
+            Self::OpenLock { level, iid, colors } => {
+                write!(f, "open the {} lock {} in {}", colors.join("/"), iid, level)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SolveReport {
+    /// Shortest order of pick-ups and unlocks that reaches the goal, if any.
+    pub solution: Option<Vec<Step>>,
+    /// Step sequences after which the goal can no longer be reached. Only the
+    /// first fatal step of each is included, shortest first.
+    pub soft_locks: Vec<Vec<Step>>,
+    /// Number of puzzle states searched.
+    pub states: usize,
+    /// Whether the search stopped at `MAX_STATES` before seeing every state.
+    pub truncated: bool,
+}
+
+struct LevelGraph {
+    identifier: String,
+    size: IVec2,
+    blocked: HashSet<IVec2>,
+    spawn: Option<IVec2>,
+    entrances: HashMap<String, IVec2>,
+    /// Cell, target level and where the player arrives in it.
+    switches: HashMap<IVec2, (usize, IVec2)>,
+}
+
+struct KeyItem {
+    level: usize,
+    cell: IVec2,
+    color: String,
+    iid: String,
+}
+
+struct Gate {
+    level: usize,
+    cell: IVec2,
+    required: Vec<(String, u32)>,
+    consumes_keys: bool,
+    iid: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct State {
+    level: usize,
+    /// Smallest cell of the area the player is in, so that states that only
+    /// differ in where exactly the player stands are the same.
+    cell: IVec2,
+    collected: Vec<bool>,
+    opened: Vec<bool>,
+}
+
+struct Puzzle {
+    levels: Vec<LevelGraph>,
+    keys: Vec<KeyItem>,
+    locks: Vec<Gate>,
+    goal: usize,
+}
+
+impl LevelGraph {
+    fn new(project: &LdtkJson, level: &Level, semantics: &IntGridSemantics) -> Self {
+        let size = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .next()
+            .map_or(IVec2::ZERO, |layer| IVec2::new(layer.c_wid, layer.c_hei));
+        let blocked = semantics
+            .level_cells(project, level)
+            .into_iter()
+            // Ability pickups aren't tracked, so terrain that needs one, like
+            // water, is treated as a wall: levels mustn't need it to be won.
+            .filter(|(_, rule)| rule.blocks || rule.requires.is_some())
+            .map(|(cell, _)| cell)
+            .collect();
+        let entrances = entities(level, "Entrance")
+            .filter_map(|e| Some((e.get_maybe_enum_field("name").ok()?.clone()?, e.grid)))
+            .collect();
+        Self {
+            identifier: level.identifier.clone(),
+            size,
+            blocked,
+            spawn: entities(level, "PlayerSpawnPoint").next().map(|e| e.grid),
+            entrances,
+            switches: HashMap::new(),
+        }
+    }
+
+    fn in_bounds(&self, cell: IVec2) -> bool {
+        cell.x >= 0 && cell.y >= 0 && cell.x < self.size.x && cell.y < self.size.y
+    }
+}
+
+impl Puzzle {
+    fn new(project: &LdtkJson, semantics: &IntGridSemantics) -> Result<(Self, State), SolveError> {
+        let mut levels: Vec<LevelGraph> = project
+            .levels
+            .iter()
+            .map(|level| LevelGraph::new(project, level, semantics))
+            .collect();
+        let index: HashMap<&str, usize> = project
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(i, level)| (level.identifier.as_str(), i))
+            .collect();
+        let goal = *index.get(GOAL_LEVEL).ok_or(SolveError::MissingGoalLevel)?;
+
+        let mut keys = Vec::new();
+        let mut locks = Vec::new();
+        for (i, level) in project.levels.iter().enumerate() {
+            for switch in entities(level, "SwitchLevel") {
+                let field = |name| switch.get_maybe_enum_field(name).ok().and_then(Option::as_ref);
+                let Some(&target) = field("to_level").and_then(|to| index.get(to.as_str())) else {
+                    continue;
+                };
+                // Without a matching entrance, the player stays where the
+                // target level spawns them.
+                let arrival = field("entrance")
+                    .and_then(|name| levels[target].entrances.get(name))
+                    .copied()
+                    .or(levels[target].spawn);
+                match arrival {
+                    Some(arrival) if target != i => {
+                        levels[i].switches.insert(switch.grid, (target, arrival));
+                    },
+                    _ => {},
+                }
+            }
+            for key in entities(level, "Key") {
+                if let Ok(Some(color)) = key.get_maybe_enum_field(KEY_TYPE_FIELD) {
+                    keys.push(KeyItem {
+                        level: i,
+                        cell: key.grid,
+                        color: color.clone(),
+                        iid: key.iid.clone(),
+                    });
+                }
+            }
+            for lock in entities(level, "Lock") {
+                if let Ok(Some(color)) = lock.get_maybe_enum_field(KEY_TYPE_FIELD) {
+                    let mut required: Vec<_> = lock_requirements(lock, color).into_iter().collect();
+                    required.sort();
+                    locks.push(Gate {
+                        level: i,
+                        cell: lock.grid,
+                        required,
+                        consumes_keys: lock.get_bool_field("ConsumesKeys").map_or(true, |c| *c),
+                        iid: lock.iid.clone(),
+                    });
+                }
+            }
+        }
+
+        let start_level = project.levels.get(START_LEVEL_INDEX).ok_or(SolveError::MissingStartLevel)?;
+        let spawn = levels[START_LEVEL_INDEX].spawn.ok_or_else(|| SolveError::MissingSpawnPoint {
+            level: start_level.identifier.clone(),
+        })?;
+        let puzzle = Self {
+            goal,
+            levels,
+            keys,
+            locks,
+        };
+        let mut start = State {
+            level: START_LEVEL_INDEX,
+            cell: spawn,
+            collected: vec![false; puzzle.keys.len()],
+            opened: vec![false; puzzle.locks.len()],
+        };
+        start.cell = puzzle.area_root(&start, START_LEVEL_INDEX, spawn);
+        Ok((puzzle, start))
+    }
+
+    fn walkable(&self, state: &State, level: usize, cell: IVec2) -> bool {
+        let graph = &self.levels[level];
+        graph.in_bounds(cell)
+            && !graph.blocked.contains(&cell)
+            && !self
+                .locks
+                .iter()
+                .enumerate()
+                .any(|(i, lock)| !state.opened[i] && lock.level == level && lock.cell == cell)
+    }
+
+    /// Smallest cell that can be walked to from `cell` without leaving the
+    /// level.
+    fn area_root(&self, state: &State, level: usize, cell: IVec2) -> IVec2 {
+        let mut seen = HashSet::from([cell]);
+        let mut queue = VecDeque::from([cell]);
+        let mut root = cell;
+        while let Some(current) = queue.pop_front() {
+            if (current.y, current.x) < (root.y, root.x) {
+                root = current;
+            }
+            for offset in NEIGHBOURS {
+                let next = current + offset;
+                if self.walkable(state, level, next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        root
+    }
+
+    /// Every cell the player can walk to in `state`, across level switches.
+    fn reachable(&self, state: &State) -> HashSet<(usize, IVec2)> {
+        let start = (state.level, state.cell);
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some((level, cell)) = queue.pop_front() {
+            let mut next: Vec<(usize, IVec2)> = NEIGHBOURS
+                .iter()
+                .map(|offset| (level, cell + *offset))
+                .filter(|(level, cell)| self.walkable(state, *level, *cell))
+                .collect();
+            if let Some(arrival) = self.levels[level].switches.get(&cell) {
+                next.push(*arrival);
+            }
+            for position in next {
+                if seen.insert(position) {
+                    queue.push_back(position);
+                }
+            }
+        }
+        seen
+    }
+
+    fn keys_in_hand(&self, state: &State, color: &str) -> u32 {
+        let collected = self
+            .keys
+            .iter()
+            .zip(state.collected.iter())
+            .filter(|(key, collected)| **collected && key.color == color)
+            .count() as u32;
+        let spent: u32 = self
+            .locks
+            .iter()
+            .zip(state.opened.iter())
+            .filter(|(lock, opened)| **opened && lock.consumes_keys)
+            .flat_map(|(lock, _)| lock.required.iter())
+            .filter(|(c, _)| c == color)
+            .map(|(_, count)| count)
+            .sum();
+        collected - spent
+    }
+
+    /// Every pick-up and unlock possible in `state`, with the state it leads
+    /// to.
+    fn successors(&self, state: &State, reachable: &HashSet<(usize, IVec2)>) -> Vec<(Step, State)> {
+        // The player stands on or next to what they interact with.
+        let standing_spots = |level: usize, cell: IVec2| {
+            std::iter::once(cell)
+                .chain(NEIGHBOURS.iter().map(move |offset| cell + *offset))
+                .filter(move |spot| reachable.contains(&(level, *spot)))
+        };
+        let mut successors = Vec::new();
+        for (i, key) in self.keys.iter().enumerate().filter(|(i, _)| !state.collected[*i]) {
+            let Some(spot) = standing_spots(key.level, key.cell).next() else {
+                continue;
+            };
+            let mut next = state.clone();
+            next.collected[i] = true;
+            next.level = key.level;
+            next.cell = self.area_root(&next, key.level, spot);
+            let step = Step::PickUpKey {
+                level: self.levels[key.level].identifier.clone(),
+                iid: key.iid.clone(),
+                color: key.color.clone(),
+            };
+            successors.push((step, next));
+        }
+        for (i, lock) in self.locks.iter().enumerate().filter(|(i, _)| !state.opened[*i]) {
+            let can_open = lock
+                .required
+                .iter()
+                .all(|(color, needed)| self.keys_in_hand(state, color) >= *needed);
+            let Some(spot) = standing_spots(lock.level, lock.cell).next() else {
+                continue;
+            };
+            if !can_open {
+                continue;
+            }
+            let mut next = state.clone();
+            next.opened[i] = true;
+            next.level = lock.level;
+            next.cell = self.area_root(&next, lock.level, spot);
+            let step = Step::OpenLock {
+                level: self.levels[lock.level].identifier.clone(),
+                iid: lock.iid.clone(),
+                colors: lock.required.iter().map(|(color, _)| color.clone()).collect(),
+            };
+            successors.push((step, next));
+        }
+        successors
+    }
+}
+
+/// Steps taken to reach state `index`, following `parents` back to the start.
+fn path_to(index: usize, parents: &[Option<(usize, Step)>]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut current = index;
+    while let Some((parent, step)) = &parents[current] {
+        steps.push(step.clone());
+        current = *parent;
+    }
+    steps.reverse();
+    steps
+}
+
+/// Searches every puzzle state reachable from the start of the game.
+pub fn solve(project: &LdtkJson, semantics: &IntGridSemantics) -> Result<SolveReport, SolveError> {
+    let (puzzle, start) = Puzzle::new(project, semantics)?;
+
+    let mut states = vec![start.clone()];
+    let mut index = HashMap::from([(start, 0)]);
+    let mut parents: Vec<Option<(usize, Step)>> = vec![None];
+    let mut edges: Vec<(usize, Step, usize)> = Vec::new();
+    let mut won = HashSet::new();
+    let mut queue = VecDeque::from([0]);
+    let mut truncated = false;
+    while let Some(current) = queue.pop_front() {
+        let reachable = puzzle.reachable(&states[current]);
+        if reachable.iter().any(|(level, _)| *level == puzzle.goal) {
+            won.insert(current);
+            continue;
+        }
+        for (step, next) in puzzle.successors(&states[current], &reachable) {
+            let next = match index.get(&next) {
+                Some(next) => *next,
+                None if states.len() >= MAX_STATES => {
+                    truncated = true;
+                    continue;
+                },
+                None => {
+                    index.insert(next.clone(), states.len());
+                    states.push(next);
+                    parents.push(Some((current, step.clone())));
+                    queue.push_back(states.len() - 1);
+                    states.len() - 1
+                },
+            };
+            edges.push((current, step, next));
+        }
+    }
+
+    // States from which the goal can still be reached.
+    let mut winnable = won.clone();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (from, _, to) in edges.iter() {
+            if winnable.contains(to) && winnable.insert(*from) {
+                changed = true;
+            }
+        }
+    }
+
+    // Breadth first, so the first winning state has the shortest path.
+    let solution = (0..states.len()).find(|i| won.contains(i)).map(|i| path_to(i, &parents));
+    let mut seen = HashSet::new();
+    let mut soft_locks: Vec<Vec<Step>> = edges
+        .iter()
+        .filter(|(from, _, to)| winnable.contains(from) && !winnable.contains(to))
+        .filter(|(_, _, to)| seen.insert(*to))
+        .map(|(from, step, _)| {
+            let mut steps = path_to(*from, &parents);
+            steps.push(step.clone());
+            steps
+        })
+        .collect();
+    soft_locks.sort_by_key(|steps| steps.len());
+
+    Ok(SolveReport {
+        solution,
+        soft_locks,
+        states: states.len(),
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farms() -> LdtkJson {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/farms.ldtk");
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn farms_can_be_finished() {
+        let report = solve(&farms(), &IntGridSemantics::default()).unwrap();
+        assert!(!report.truncated);
+        assert!(report.soft_locks.is_empty());
+
+        // Both stacked wheat locks keep the key, so it opens one after the
+        // other.
+        let solution = report.solution.unwrap();
+        assert_eq!(solution.len(), 3);
+        assert!(matches!(&solution[0], Step::PickUpKey { color, .. } if color == "Blue"));
+        assert!(solution[1..].iter().all(|step| matches!(
+            step,
+            Step::OpenLock { level, colors, .. } if level == "Wheat_Fields" && colors == &["Blue"]
+        )));
+        assert_ne!(solution[1], solution[2]);
+    }
+
+    #[test]
+    fn wasting_the_only_key_is_a_soft_lock() {
+        let mut project = farms();
+        // A blue lock that uses up its key in Crossroads, next to the key.
+        let mut lock = project
+            .levels
+            .iter()
+            .flat_map(|level| level.layer_instances.iter().flatten())
+            .flat_map(|layer| layer.entity_instances.iter())
+            .find(|e| e.identifier == "Lock")
+            .cloned()
+            .unwrap();
+        lock.field_instances.retain(|field| field.identifier != "ConsumesKeys");
+        let crossroads = project.levels.iter_mut().find(|l| l.identifier == "Crossroads").unwrap();
+        let entities = crossroads
+            .layer_instances
+            .iter_mut()
+            .flatten()
+            .find(|layer| layer.identifier == "Entities")
+            .unwrap();
+        let key = entities.entity_instances.iter().find(|e| e.identifier == "Key").unwrap().grid;
+        entities.entity_instances.push(bevy_ecs_ldtk::ldtk::EntityInstance {
+            iid: "wasteful-lock".to_string(),
+            grid: key + IVec2::X,
+            ..lock
+        });
+
+        let report = solve(&project, &IntGridSemantics::default()).unwrap();
+        assert!(report.solution.is_some());
+        let soft_lock = report.soft_locks.first().unwrap();
+        assert!(matches!(soft_lock.last(), Some(Step::OpenLock { iid, .. }) if iid == "wasteful-lock"));
+    }
+}
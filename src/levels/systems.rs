@@ -4,101 +4,155 @@ use bevy_ecs_ldtk::prelude::*;
 
 use crate::levels::{
     components::*,
+    events::*,
     ldtk_entities::*,
+    int_grid::*,
     utils::*,
+    validation::validate_project,
+    world_layout::*,
 };
 
-use crate::constants::*;
+use crate::interaction::prelude::*;
+use crate::player_movement::{Abilities, TerrainCost};
 
-
-pub(crate) fn cache_locks_and_keys(
-    mut keys: ResMut<Keys>,
-    mut locks: ResMut<Locks>,
+/// Adds the colliders and terrain of every spawned level, and drops those of
+/// despawned ones. With a `Separate` layout only one level is loaded, so a
+/// spawn replaces everything.
+pub(crate) fn cache_collider_location(
+    mut level_colliders: ResMut<Colliders>,
+    mut terrain: ResMut<Terrain>,
     mut level_events: EventReader<LevelEvent>,
-    key_query: Query<(&GridCoords, &LockKeyColor), With<Key>>,
-    lock_query: Query<(&GridCoords, &LockKeyColor), With<Lock>>,
+    semantics: Res<IntGridSemantics>,
+    layout: Res<LevelLayout>,
+    locks: Query<(Entity, &GridCoords), With<Lock>>,
+    world_coords: WorldCoords,
+    ldtk_project_entities: Query<&Handle<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
 ) {
     for level_event in level_events.iter() {
-        if let LevelEvent::Spawned(_) = level_event {
-            let mut color_location_pairing = HashMap::new();
-            for (gc, color) in key_query.iter() {
-                color_location_pairing.insert(*color, *gc);
+        let (LevelEvent::Spawned(level_iid) | LevelEvent::Despawned(level_iid)) = level_event else {
+            continue;
+        };
+        let Some(ldtk_project) = ldtk_project_entities
+            .get_single()
+            .ok()
+            .and_then(|handle| ldtk_project_assets.get(handle))
+        else {
+            continue;
+        };
+        let Some(level) = ldtk_project.get_level(&LevelSelection::Iid(level_iid.to_string())) else {
+            error!("Level {} is not in the loaded project", level_iid);
+            continue;
+        };
+        let loaded = LoadedLevel::new(level, *layout);
+        if let LevelEvent::Despawned(_) = level_event {
+            if *layout == LevelLayout::Seamless {
+                level_colliders.remove_level(&loaded);
+                terrain.remove_level(&loaded);
             }
-            *keys = Keys {
-                color_location_pairing,
-            };
+            continue;
+        }
 
-            color_location_pairing = HashMap::new();
-            for (gc, color) in lock_query.iter() {
-                color_location_pairing.insert(*color, *gc);
-            }
-            *locks = Locks {
-                color_location_pairing,
-            };
-            info!("Keys: {:?}", keys);
-            info!("Locks: {:?}", locks);
+        debug!("Spawned level {}", level_iid);
+        if *layout == LevelLayout::Separate {
+            *level_colliders = Colliders::default();
+            *terrain = Terrain::default();
         }
+        let cells: HashMap<GridCoords, CellRule> = semantics
+            .level_cells(&ldtk_project.project, level)
+            .into_iter()
+            .map(|(cell, rule)| (loaded.cell_from_ldtk(cell), rule))
+            .collect();
+        let level_locks = locks
+            .iter()
+            .filter(|(entity, _)| world_coords.level_iid(*entity) == Some(level_iid.as_str()))
+            .map(|(entity, gc)| world_coords.to_world(entity, *gc));
+        level_colliders.collider_locations.extend(
+            cells
+                .iter()
+                .filter(|(_, rule)| rule.blocks)
+                .map(|(gc, _)| *gc)
+                .chain(level_locks)
+        );
+        level_colliders.terrain_costs.extend(
+            cells
+                .iter()
+                .filter(|(_, rule)| rule.speed != 1. || rule.requires.is_some())
+                .map(|(gc, rule)| (*gc, TerrainCost {
+                    speed: rule.speed,
+                    requires: rule.requires.clone(),
+                }))
+        );
+        terrain.cells.extend(cells);
     }
 }
 
-pub(crate) fn cache_collider_location(
-    mut level_colliders: ResMut<Colliders>,
+/// Updates `CurrentLevel` and `LevelBounds` once the selected level has
+/// spawned. In a seamless world it may already be spawned as a neighbour.
+pub(crate) fn track_current_level(
+    mut current_level: ResMut<CurrentLevel>,
+    mut bounds: ResMut<LevelBounds>,
     mut level_events: EventReader<LevelEvent>,
-    unwalkables: Query<&GridCoords, With<Unwalkable>>,
-    locks: Query<&GridCoords, With<Lock>>,
+    level_selection: Res<LevelSelection>,
+    layout: Res<LevelLayout>,
+    loaded: Res<LoadedLevels>,
     ldtk_project_entities: Query<&Handle<LdtkAsset>>,
     ldtk_project_assets: Res<Assets<LdtkAsset>>,
 ) {
-    for level_event in level_events.iter() {
-        if let LevelEvent::Spawned(level_iid) = level_event {
-            debug!("Spawned level {}", level_iid);
-            let ldtk_project = ldtk_project_assets
-                .get(ldtk_project_entities.single())
-                .expect("LdtkProject should be loaded when level is spawned");
-            let level = ldtk_project
-                .get_level(&LevelSelection::Iid(level_iid.to_string()))
-                .expect("spawned level should exist in project");
-            let collider_locations = unwalkables.iter().chain(locks.iter()).copied().collect();
-            let new_collider_locations = Colliders {
-                collider_locations,
-                level_width: level.px_wid / GRID_SIZE as i32,
-                level_height: level.px_hei / GRID_SIZE as i32,
-            };
-            *level_colliders = new_collider_locations;
-        }
+    let spawned: Vec<&String> = level_events
+        .iter()
+        .filter_map(|level_event| match level_event {
+            LevelEvent::Spawned(level_iid) => Some(level_iid),
+            _ => None,
+        })
+        .collect();
+    let Some(selected) = ldtk_project_entities
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+        .and_then(|ldtk_project| ldtk_project.get_level(&level_selection))
+    else {
+        return;
+    };
+    let already_loaded = level_selection.is_changed()
+        && selected.iid != current_level.iid
+        && loaded.levels.contains_key(&selected.iid);
+    if already_loaded || spawned.contains(&&selected.iid) {
+        current_level.iid = selected.iid.clone();
+        bounds.rect = LoadedLevel::new(selected, *layout).rect();
     }
 }
 
-pub(crate) fn cache_goal_location(
-    mut level_goals: ResMut<NextLevels>,
+pub(crate) fn restore_level_state(
+    level_states: Res<LevelStates>,
     mut level_events: EventReader<LevelEvent>,
-    next_level_query: Query<&GridCoords, With<NextLevel>>
+    mut level_colliders: ResMut<Colliders>,
+    persistent: Query<(Entity, &EntityIid, Option<&GridCoords>, Option<&Lock>), With<Persistent>>,
+    world_coords: WorldCoords,
+    mut commands: Commands,
 ) {
     for level_event in level_events.iter() {
-        if let LevelEvent::Spawned(_) = level_event {
-            // print all the colors in the enum of the level
-            let locations: HashSet<GridCoords> = next_level_query.iter().copied().collect();
-            *level_goals = NextLevels {
-                locations
+        if let LevelEvent::Spawned(level_iid) = level_event {
+            let Some(state) = level_states.get(level_iid) else {
+                continue;
             };
-        }
-    }
-}
-
-pub(crate) fn cache_entrance_location(
-    mut entrances: ResMut<Entrances>,
-    mut level_events: EventReader<LevelEvent>,
-    entrance_query: Query<(&GridCoords, &Entrance)>
-) {
-    for level_event in level_events.iter() {
-        if let LevelEvent::Spawned(_) = level_event {
-            let mut locations = HashMap::new();
-            for (coord, entr) in entrance_query.iter() {
-                locations.insert(entr.name.clone(), *coord);
+            let mut opened_locks = HashSet::new();
+            let mut closed_locks = HashSet::new();
+            for (entity, iid, gc, lock) in persistent.iter() {
+                let despawned = state.despawned.contains(iid.as_str());
+                if let (Some(_), Some(gc)) = (lock, gc) {
+                    let gc = world_coords.to_world(entity, *gc);
+                    if despawned { opened_locks.insert(gc) } else { closed_locks.insert(gc) };
+                }
+                if !despawned {
+                    continue;
+                }
+                commands.entity(entity).despawn_recursive();
+                debug!("Entity {} was already removed from level {}", iid.as_str(), level_iid);
             }
-            debug!("Entrances: {:?}", locations);
-            *entrances = Entrances {
-                locations
+            // A cell with a stacked lock that is still closed stays blocked.
+            for gc in opened_locks.difference(&closed_locks) {
+                level_colliders.collider_locations.remove(gc);
             }
         }
     }
@@ -106,93 +160,297 @@ pub(crate) fn cache_entrance_location(
 
 pub(crate) fn check_for_goals<Player: Component>(
     player_position: Query<&Transform, With<Player>>,
-    goals: Query<(&Transform, &NextLevel), Without<Player>>,
+    goals: Query<(Entity, &GlobalTransform, &NextLevel), Without<Player>>,
+    level: Res<LevelSelection>,
+    mut standing_on: Local<Option<Entity>>,
+    mut switch_requested: EventWriter<LevelSwitchRequested>,
+) {
+    let Ok(player_tf) = player_position.get_single() else {
+        return;
+    };
+    let grid_tf = to_grid_coords(*player_tf);
+    // A `SwitchLevel` without a target can't go anywhere.
+    let goal = goals
+        .iter()
+        .filter(|(_, _, nl)| !nl.next_level.is_empty())
+        .find(|(_, tf, _)| to_grid_coords(tf.compute_transform()) == grid_tf);
+    // The player keeps standing on the goal until the next level has
+    // spawned, so the switch is only requested when they step onto it.
+    let previous = std::mem::replace(&mut *standing_on, goal.map(|(entity, _, _)| entity));
+    let Some((entity, _, nl)) = goal else {
+        return;
+    };
+    if previous == Some(entity) || *level == LevelSelection::Identifier(nl.next_level.clone()) {
+        return;
+    }
+    info!("Player stepped on goal! {:?}", nl);
+    switch_requested.send(LevelSwitchRequested {
+        to: nl.next_level.clone(),
+        entrance: nl.entrance.clone(),
+    });
+}
+
+pub(crate) fn queue_level_switch(
+    mut switch_requested: EventReader<LevelSwitchRequested>,
+    mut pending: ResMut<PendingLevelSwitch>,
+) {
+    for request in switch_requested.iter() {
+        *pending = PendingLevelSwitch {
+            to: request.to.clone(),
+            entrance: request.entrance.clone(),
+            pending: true,
+        };
+    }
+}
+
+pub(crate) fn switch_level(
+    gate: Res<LevelSwitchGate>,
+    mut pending: ResMut<PendingLevelSwitch>,
     mut level: ResMut<LevelSelection>,
     mut came_from: ResMut<CameFrom>,
 ) {
-    if let Ok(player_tf) = player_position.get_single() {
-        let grid_tf = to_grid_coords(*player_tf);
-        for (tf, nl) in goals.iter() {
-            let goal_tf = to_grid_coords(*tf);
-            let diff = grid_tf - goal_tf;
-            if diff.x == 0 && diff.y == 0 {
-                info!("Player stepped on goal! {:?}", nl);
-                *level = LevelSelection::Identifier(nl.next_level.clone());
-                came_from.from = nl.entrance.clone();
-            }
-        }
+    if !pending.pending || gate.is_held() {
+        return;
     }
+    pending.pending = false;
+    *level = LevelSelection::Identifier(pending.to.clone());
+    came_from.from = pending.entrance.clone();
 }
 
+/// Places the player at the entrance they came through, once the selected
+/// level is in place.
 pub(crate) fn move_player_to_entrance<Player: Component>(
-    mut level_events: EventReader<LevelEvent>,
+    current_level: Res<CurrentLevel>,
     mut player_position: Query<&mut Transform, With<Player>>,
-    entrance_query: Query<(&GridCoords, &Entrance)>,
+    entrance_query: Query<(Entity, &GridCoords, &Entrance)>,
+    world_coords: WorldCoords,
     came_from: Res<CameFrom>,
+    mut player_entered: EventWriter<PlayerEnteredLevel>,
 ) {
-    for level_event in level_events.iter() {
-        if let Ok(mut tf) = player_position.get_single_mut() {
-            let entrances: Vec<(&GridCoords, &Entrance)> = entrance_query.iter().collect();
-            if let LevelEvent::Spawned(_) = level_event {
-                if let Some((gc, _)) = entrances.iter().find(|(_, ent)| ent.name == came_from.from) {
-                    tf.translation = to_translation(**gc, tf.translation.z);
-                } else {
-                    error!("Wanted to find {:?}, did not find entrance!?", came_from.from);
-                }
-            }
+    if !current_level.is_changed() || current_level.iid.is_empty() {
+        return;
+    }
+    if let Ok(mut tf) = player_position.get_single_mut() {
+        let entrance = entrance_query.iter().find(|(entity, _, ent)| {
+            ent.name == came_from.from && world_coords.level_iid(*entity) == Some(current_level.iid.as_str())
+        });
+        if let Some((entity, gc, _)) = entrance {
+            tf.translation = to_translation(world_coords.to_world(entity, *gc), tf.translation.z);
+        } else {
+            error!("Wanted to find {:?}, did not find entrance!?", came_from.from);
         }
+        player_entered.send(PlayerEnteredLevel {
+            level_iid: current_level.iid.clone(),
+            entrance: entrance.map(|(_, _, ent)| ent.name.clone()),
+            scrolled: false,
+        });
     }
 }
 
-pub(crate) fn pickup_key<Player: Component>(
+pub(crate) fn pickup_key(
+    mut interactions: EventReader<Interacted>,
     mut carried_keys: ResMut<CarriedKeys>,
-    player_position: Query<&Transform, With<Player>>,
-    keys: Query<(Entity, &GridCoords, &LockKeyColor), With<Key>>,
+    mut level_states: ResMut<LevelStates>,
+    keys: Query<(&EntityIid, &LockKeyColor), With<Key>>,
+    world_coords: WorldCoords,
+    mut key_picked_up: EventWriter<KeyPickedUp>,
     mut commands: Commands,
 ) {
-    if let Ok(tf) = player_position.get_single() {
-        let grid_tf = to_grid_coords(*tf);
-        for (entity, gc, color) in keys.iter() {
-            let diff = grid_tf - *gc;
-            if diff.x.abs() <= 1 && diff.y.abs() <= 1 {
-                carried_keys.keys.insert(*color);
-                commands.entity(entity).despawn_recursive();
-                info!("Picked up key {:?}", color);
-            }
+    for Interacted { entity } in interactions.iter() {
+        let Ok((iid, color)) = keys.get(*entity) else {
+            continue;
+        };
+        carried_keys.add(color.clone());
+        if let Some(level_iid) = world_coords.level_iid(*entity) {
+            level_states.record_despawned(level_iid, iid);
+        }
+        commands.entity(*entity).despawn_recursive();
+        key_picked_up.send(KeyPickedUp { color: color.clone() });
+        info!("Picked up key {:?}", color);
+    }
+}
+
+pub(crate) fn pickup_ability(
+    mut interactions: EventReader<Interacted>,
+    mut abilities: ResMut<Abilities>,
+    mut level_states: ResMut<LevelStates>,
+    pickups: Query<(&EntityIid, &AbilityPickup)>,
+    world_coords: WorldCoords,
+    mut commands: Commands,
+) {
+    for Interacted { entity } in interactions.iter() {
+        let Ok((iid, pickup)) = pickups.get(*entity) else {
+            continue;
+        };
+        abilities.grant(pickup.ability.clone());
+        if let Some(level_iid) = world_coords.level_iid(*entity) {
+            level_states.record_despawned(level_iid, iid);
         }
+        commands.entity(*entity).despawn_recursive();
+        info!("Picked up ability {}", pickup.ability);
     }
 }
 
-pub(crate) fn is_near_lock<Player: Component>(
+pub(crate) fn open_lock(
+    mut interactions: EventReader<Interacted>,
     mut carried_keys: ResMut<CarriedKeys>,
-    player_position: Query<&Transform, With<Player>>,
-    locks: Query<(Entity, &GridCoords, &LockKeyColor), With<Lock>>,
+    mut level_states: ResMut<LevelStates>,
+    locks: Query<(Entity, &EntityIid, &GridCoords, &Lock)>,
+    world_coords: WorldCoords,
     mut level_colliders: ResMut<Colliders>,
+    mut lock_opened: EventWriter<LockOpened>,
     mut commands: Commands,
 ) {
-    if let Ok(tf) = player_position.get_single() {
-        let grid_tf = to_grid_coords(*tf);
-        for (entity, gc, color) in locks.iter() {
-            let diff = grid_tf - *gc;
-            if diff.x.abs() <= 3 && diff.y.abs() <= 3 {
-                if carried_keys.keys.contains(color) {
-                    commands.entity(entity).despawn_recursive();
-                    level_colliders.collider_locations.remove(gc);
-                    info!("Unlocked lock {:?}", color);
-                }
-            }
+    for Interacted { entity } in interactions.iter() {
+        let Ok((_, iid, gc, lock)) = locks.get(*entity) else {
+            continue;
+        };
+        if !carried_keys.can_open(lock) {
+            info!("Lock {:?} needs {}", gc, lock.describe_requirement());
+            continue;
+        }
+        if lock.consumes_keys {
+            carried_keys.spend(lock);
+        }
+        commands.entity(*entity).despawn_recursive();
+        let gc = world_coords.to_world(*entity, *gc);
+        // Locks can be stacked, and the cell stays blocked until the last
+        // one is open.
+        let stacked = locks.iter().any(|(other, _, other_gc, _)| {
+            other != *entity && world_coords.to_world(other, *other_gc) == gc
+        });
+        if !stacked {
+            level_colliders.collider_locations.remove(&gc);
         }
+        if let Some(level_iid) = world_coords.level_iid(*entity) {
+            level_states.record_despawned(level_iid, iid);
+        }
+        lock_opened.send(LockOpened { color: lock.color.clone(), coords: gc });
+        info!("Unlocked lock {:?}", lock.color);
     }
 }
 
+pub(crate) fn spawn_sign_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..TextBundle::from_section("", TextStyle {
+                font_size: 16.,
+                color: Color::WHITE,
+                ..default()
+            })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(32.),
+                left: Val::Px(8.),
+                right: Val::Px(8.),
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.7))
+        },
+        SignText,
+    ));
+}
+
+/// Shows the text of the sign the player interacts with, until they turn or
+/// walk away from it.
+pub(crate) fn read_sign(
+    mut interactions: EventReader<Interacted>,
+    signs: Query<&Sign>,
+    target: Res<InteractionTarget>,
+    mut reading: ResMut<ReadingSign>,
+    mut sign_text: Query<(&mut Text, &mut Visibility), With<SignText>>,
+) {
+    for Interacted { entity } in interactions.iter() {
+        if signs.contains(*entity) {
+            reading.0 = Some(*entity);
+        }
+    }
+    if reading.0.is_some() && reading.0 != target.0 {
+        reading.0 = None;
+    }
+    if !reading.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = sign_text.get_single_mut() else {
+        return;
+    };
+    match reading.0.and_then(|entity| signs.get(entity).ok()) {
+        Some(sign) => {
+            text.sections[0].value = sign.text.clone();
+            *visibility = Visibility::Inherited;
+        },
+        None => {
+            text.sections[0].value.clear();
+            *visibility = Visibility::Hidden;
+        },
+    }
+}
+
+
+/// Validates the LDtk project whenever it is loaded or hot-reloaded, and logs
+/// every problem found.
+pub(crate) fn report_project_problems(
+    mut asset_events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+) {
+    for event in asset_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(ldtk_asset) = ldtk_assets.get(handle) else {
+            continue;
+        };
+        let problems = validate_project(&ldtk_asset.project);
+        for problem in problems.iter() {
+            error!("Level problem: {}", problem);
+        }
+        info!("Validated levels, found {} problem(s)", problems.len());
+    }
+}
+
+/// Replaces the default `IntGridSemantics` with `INT_GRID_SEMANTICS_PATH`, if
+/// there is one.
+pub(crate) fn load_int_grid_semantics(mut commands: Commands) {
+    if !std::path::Path::new(INT_GRID_SEMANTICS_PATH).exists() {
+        info!("No {} found, using default IntGrid semantics", INT_GRID_SEMANTICS_PATH);
+        return;
+    }
+    match IntGridSemantics::load(INT_GRID_SEMANTICS_PATH) {
+        Ok(semantics) => commands.insert_resource(semantics),
+        Err(e) => error!("Could not read {}, using default IntGrid semantics: {}", INT_GRID_SEMANTICS_PATH, e),
+    }
+}
+
+/// Hides the player while they stand in a cell that `hides_player`, such as
+/// tall wheat.
+pub(crate) fn hide_player_in_foliage<Player: Component>(
+    terrain: Res<Terrain>,
+    mut player: Query<(Entity, &Transform, &mut TextureAtlasSprite, Option<&HiddenInFoliage>), With<Player>>,
+    mut commands: Commands,
+) {
+    let Ok((entity, tf, mut sprite, hidden)) = player.get_single_mut() else {
+        return;
+    };
+    let hidden = hidden.is_some();
+    let hides = terrain.at(&to_grid_coords(*tf)).hides_player;
+    if hides && !hidden {
+        commands.entity(entity).insert(HiddenInFoliage);
+        sprite.color.set_a(HIDDEN_ALPHA);
+    } else if !hides && hidden {
+        commands.entity(entity).remove::<HiddenInFoliage>();
+        sprite.color.set_a(1.);
+    }
+}
 
 pub(crate) fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    project: Res<LevelProject>,
 ) {
     commands.spawn(
         LdtkWorldBundle {
-            ldtk_handle: asset_server.load("farms.ldtk"),
+            ldtk_handle: asset_server.load(project.0.as_str()),
             ..default()
         }
     );
@@ -6,7 +6,7 @@ use crate::constants::GRID_SIZE_IVEC;
 pub (crate) fn to_grid_coords(
     tf: Transform
 ) -> GridCoords {
-    bevy_ecs_ldtk::utils::translation_to_grid_coords(tf.translation.truncate(), GRID_SIZE_IVEC)
+    crate::utils::translation_to_grid_coords(tf.translation.truncate())
 }
 
 pub (crate) fn to_translation(
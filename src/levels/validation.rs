@@ -0,0 +1,377 @@
+// File: validation.rs
+//
+// Checks an LDtk project for problems that would otherwise only show up while
+// playing: level switches into levels or entrances that don't exist, locks
+// whose keys can't be reached and levels without a player spawn point. Every
+// problem is collected so that they can all be fixed in one go.
+//
+// This module only depends on the LDtk data types, so that the
+// `validate-levels` binary can use it without starting the game.
+
+use bevy_ecs_ldtk::ldtk::{EntityInstance, LdtkJson, Level};
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Index of the level a new game starts in.
+pub const START_LEVEL_INDEX: usize = 1;
+
+/// The LDtk enum that defines the key types.
+pub const KEY_TYPE_ENUM: &str = "LockColor";
+
+/// The entity field that holds a key's or lock's type.
+pub const KEY_TYPE_FIELD: &str = "LockColor";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// There is no level at `START_LEVEL_INDEX`.
+    MissingStartLevel,
+    /// The project has no `LockColor` enum.
+    MissingKeyTypes,
+    /// An entity is missing a field the game needs.
+    MissingField {
+        level: String,
+        entity: String,
+        iid: String,
+        field: &'static str,
+    },
+    /// A key or lock refers to a value that isn't in the `LockColor` enum.
+    UnknownKeyType {
+        level: String,
+        entity: String,
+        iid: String,
+        value: String,
+    },
+    /// A `SwitchLevel` leads to a level that doesn't exist.
+    UnknownLevel {
+        level: String,
+        iid: String,
+        to_level: String,
+    },
+    /// A `SwitchLevel` leads to an `Entrance` that its target level lacks.
+    UnknownEntrance {
+        level: String,
+        iid: String,
+        to_level: String,
+        entrance: String,
+    },
+    /// A level has no `PlayerSpawnPoint`, so the player disappears in it.
+    MissingSpawnPoint {
+        level: String,
+    },
+    /// A lock needs more keys of a color than can be reached from the start
+    /// level.
+    LockWithoutKey {
+        level: String,
+        iid: String,
+        color: String,
+        needed: u32,
+        reachable: u32,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStartLevel => write!(
+                f,
+                "there is no start level, the project needs at least {} levels",
+                START_LEVEL_INDEX + 1
+            ),
+            Self::MissingKeyTypes => write!(
+                f,
+                "the project has no `{}` enum, so keys and locks have no type",
+                KEY_TYPE_ENUM
+            ),
+            Self::MissingField { level, entity, iid, field } => write!(
+                f,
+                "{}: {} {} has no `{}` set",
+                level, entity, iid, field
+            ),
+            Self::UnknownKeyType { level, entity, iid, value } => write!(
+                f,
+                "{}: {} {} has `{}` = {:?}, which is not a value of the `{}` enum",
+                level, entity, iid, KEY_TYPE_FIELD, value, KEY_TYPE_ENUM
+            ),
+            Self::UnknownLevel { level, iid, to_level } => write!(
+                f,
+                "{}: SwitchLevel {} leads to level {:?}, which does not exist",
+                level, iid, to_level
+            ),
+            Self::UnknownEntrance { level, iid, to_level, entrance } => write!(
+                f,
+                "{}: SwitchLevel {} leads to entrance {:?} in {}, which has no such Entrance",
+                level, iid, entrance, to_level
+            ),
+            Self::MissingSpawnPoint { level } => write!(
+                f,
+                "{}: level has no PlayerSpawnPoint, so there is no player in it",
+                level
+            ),
+            Self::LockWithoutKey { level, iid, color, needed, reachable } => write!(
+                f,
+                "{}: Lock {} needs {} {} key(s), but only {} can be reached from the start level",
+                level, iid, needed, color, reachable
+            ),
+        }
+    }
+}
+
+pub(crate) fn entities<'a>(level: &'a Level, identifier: &'a str) -> impl Iterator<Item = &'a EntityInstance> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .filter(move |entity| entity.identifier == identifier)
+}
+
+/// Reads an enum field, recording a `MissingField` error if it isn't set.
+fn enum_field<'a>(
+    level: &Level,
+    entity: &'a EntityInstance,
+    field: &'static str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<&'a String> {
+    match entity.get_maybe_enum_field(field) {
+        Ok(Some(value)) => Some(value),
+        _ => {
+            errors.push(ValidationError::MissingField {
+                level: level.identifier.clone(),
+                entity: entity.identifier.clone(),
+                iid: entity.iid.clone(),
+                field,
+            });
+            None
+        },
+    }
+}
+
+/// Keys a lock needs per color, read from the same fields as `Lock`.
+pub(crate) fn lock_requirements(lock: &EntityInstance, color: &str) -> HashMap<String, u32> {
+    let mut required = HashMap::new();
+    let count = lock.get_int_field("KeyCount").map_or(1, |count| (*count).max(1) as u32);
+    required.insert(color.to_string(), count);
+    if let Ok(extra_keys) = lock.iter_enums_field("ExtraKeys") {
+        for extra in extra_keys {
+            *required.entry(extra.clone()).or_default() += 1;
+        }
+    }
+    required
+}
+
+/// Runs every check on `project` and returns all problems found.
+pub fn validate_project(project: &LdtkJson) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let levels: HashMap<&str, &Level> = project
+        .levels
+        .iter()
+        .map(|level| (level.identifier.as_str(), level))
+        .collect();
+    let key_types: Option<HashSet<&str>> = project
+        .defs
+        .enums
+        .iter()
+        .find(|e| e.identifier == KEY_TYPE_ENUM)
+        .map(|e| e.values.iter().map(|value| value.id.as_str()).collect());
+    if key_types.is_none() {
+        errors.push(ValidationError::MissingKeyTypes);
+    }
+
+    let mut exits: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut keys: HashMap<&str, HashMap<String, u32>> = HashMap::new();
+    let mut locks = Vec::new();
+    for level in project.levels.iter() {
+        for switch in entities(level, "SwitchLevel") {
+            let to_level = enum_field(level, switch, "to_level", &mut errors);
+            let entrance = enum_field(level, switch, "entrance", &mut errors);
+            let Some(to_level) = to_level else {
+                continue;
+            };
+            let Some(target) = levels.get(to_level.as_str()) else {
+                errors.push(ValidationError::UnknownLevel {
+                    level: level.identifier.clone(),
+                    iid: switch.iid.clone(),
+                    to_level: to_level.clone(),
+                });
+                continue;
+            };
+            exits.entry(level.identifier.as_str()).or_default().push(target.identifier.as_str());
+            let Some(entrance) = entrance else {
+                continue;
+            };
+            let found = entities(target, "Entrance")
+                .any(|e| e.get_maybe_enum_field("name").ok().and_then(Option::as_ref) == Some(entrance));
+            if !found {
+                errors.push(ValidationError::UnknownEntrance {
+                    level: level.identifier.clone(),
+                    iid: switch.iid.clone(),
+                    to_level: to_level.clone(),
+                    entrance: entrance.clone(),
+                });
+            }
+        }
+
+        for entrance in entities(level, "Entrance") {
+            enum_field(level, entrance, "name", &mut errors);
+        }
+
+        for entity in entities(level, "Key").chain(entities(level, "Lock")) {
+            let Some(color) = enum_field(level, entity, KEY_TYPE_FIELD, &mut errors) else {
+                continue;
+            };
+            if key_types.as_ref().is_some_and(|types| !types.contains(color.as_str())) {
+                errors.push(ValidationError::UnknownKeyType {
+                    level: level.identifier.clone(),
+                    entity: entity.identifier.clone(),
+                    iid: entity.iid.clone(),
+                    value: color.clone(),
+                });
+            }
+            if entity.identifier == "Key" {
+                *keys
+                    .entry(level.identifier.as_str())
+                    .or_default()
+                    .entry(color.clone())
+                    .or_default() += 1;
+            } else {
+                locks.push((level, entity, lock_requirements(entity, color)));
+            }
+        }
+    }
+
+    let Some(start) = project.levels.get(START_LEVEL_INDEX) else {
+        errors.push(ValidationError::MissingStartLevel);
+        return errors;
+    };
+
+    // Locks are ignored here; whether they can be opened in a sensible order
+    // is up to the level design.
+    let mut reachable = HashSet::from([start.identifier.as_str()]);
+    let mut queue = VecDeque::from([start.identifier.as_str()]);
+    while let Some(level) = queue.pop_front() {
+        for next in exits.get(level).into_iter().flatten() {
+            if reachable.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    // Every level needs a player, even one that nothing leads to yet: a save
+    // or a `LevelSelection` can still start the game in it.
+    for level in project.levels.iter() {
+        if entities(level, "PlayerSpawnPoint").next().is_none() {
+            errors.push(ValidationError::MissingSpawnPoint {
+                level: level.identifier.clone(),
+            });
+        }
+    }
+
+    let mut reachable_keys: HashMap<&str, u32> = HashMap::new();
+    for (_, colors) in keys.iter().filter(|(level, _)| reachable.contains(*level)) {
+        for (color, count) in colors.iter() {
+            *reachable_keys.entry(color.as_str()).or_default() += count;
+        }
+    }
+
+    for (level, lock, required) in locks {
+        let mut required: Vec<_> = required.into_iter().collect();
+        required.sort();
+        for (color, needed) in required {
+            let found = reachable_keys.get(color.as_str()).copied().unwrap_or_default();
+            if found < needed {
+                errors.push(ValidationError::LockWithoutKey {
+                    level: level.identifier.clone(),
+                    iid: lock.iid.clone(),
+                    color,
+                    needed,
+                    reachable: found,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farms() -> LdtkJson {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/farms.ldtk");
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn level_mut<'a>(project: &'a mut LdtkJson, identifier: &str) -> &'a mut Level {
+        project.levels.iter_mut().find(|l| l.identifier == identifier).unwrap()
+    }
+
+    #[test]
+    fn farms_has_no_problems() {
+        assert_eq!(validate_project(&farms()), vec![]);
+    }
+
+    #[test]
+    fn every_level_needs_a_spawn_point() {
+        let mut project = farms();
+        // Nothing leads to Treasure_Trove, it still needs a player.
+        for level in ["Finale", "Treasure_Trove"] {
+            for layer in level_mut(&mut project, level).layer_instances.iter_mut().flatten() {
+                layer.entity_instances.retain(|e| e.identifier != "PlayerSpawnPoint");
+            }
+        }
+        assert_eq!(validate_project(&project), vec![
+            ValidationError::MissingSpawnPoint {
+                level: "Treasure_Trove".to_string(),
+            },
+            ValidationError::MissingSpawnPoint {
+                level: "Finale".to_string(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn all_problems_are_reported_at_once() {
+        let mut project = farms();
+        let before = validate_project(&project);
+
+        // Send Crossroads' only exit nowhere and drop the blue key.
+        for layer in level_mut(&mut project, "Crossroads").layer_instances.iter_mut().flatten() {
+            layer.entity_instances.retain(|e| e.identifier != "Key");
+            for entity in layer.entity_instances.iter_mut().filter(|e| e.identifier == "SwitchLevel") {
+                for field in entity.field_instances.iter_mut().filter(|f| f.identifier == "to_level") {
+                    field.value = bevy_ecs_ldtk::ldtk::FieldValue::Enum(Some("Nowhere".to_string()));
+                }
+            }
+        }
+        let errors = validate_project(&project);
+
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            ValidationError::UnknownLevel { to_level, .. } if to_level == "Nowhere"
+        )));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            ValidationError::LockWithoutKey { color, reachable: 0, .. } if color == "Blue"
+        )));
+        assert!(before.iter().all(|e| errors.contains(e)));
+    }
+
+    #[test]
+    fn key_types_must_exist() {
+        let mut project = farms();
+        assert!(!validate_project(&project).iter().any(|e| matches!(
+            e,
+            ValidationError::MissingKeyTypes | ValidationError::UnknownKeyType { .. }
+        )));
+
+        project.defs.enums.iter_mut()
+            .filter(|e| e.identifier == KEY_TYPE_ENUM)
+            .for_each(|e| e.values.retain(|value| value.id != "Blue"));
+        let errors = validate_project(&project);
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            ValidationError::UnknownKeyType { value, .. } if value == "Blue"
+        )));
+    }
+}
@@ -0,0 +1,329 @@
+// File: world_layout.rs
+//
+// Levels are laid out in one world when the LDtk project uses a `GridVania`,
+// `LinearHorizontal` or `LinearVertical` world layout. Each level is spawned at
+// its world position together with its neighbours, and walking off the edge of
+// a level selects the neighbour the player walked into without respawning
+// anything. `SwitchLevel` tiles keep working as before.
+//
+// With a `Free` layout every level is spawned on its own at the origin, so
+// only one level is ever loaded.
+//
+// Either way, `Colliders`, `Terrain` and `LevelBounds` are in world grid
+// coordinates. LDtk entities keep their `GridCoords` relative to their level,
+// use `WorldCoords` to convert them.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::ldtk::{Level, WorldLayout};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::constants::GRID_SIZE;
+use crate::levels::{
+    components::*,
+    events::PlayerEnteredLevel,
+    utils::to_grid_coords,
+};
+
+/// How the levels of the project are placed, read from its world layout.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelLayout {
+    /// One level at a time, spawned at the origin.
+    #[default]
+    Separate,
+    /// Levels are spawned at their world position along with their
+    /// neighbours, and scroll into each other.
+    Seamless,
+}
+
+impl LevelLayout {
+    pub fn from_world_layout(world_layout: Option<&WorldLayout>) -> Self {
+        match world_layout {
+            Some(WorldLayout::GridVania | WorldLayout::LinearHorizontal | WorldLayout::LinearVertical) => {
+                Self::Seamless
+            },
+            _ => Self::Separate,
+        }
+    }
+}
+
+/// Where a spawned level is, in world grid cells.
+#[derive(Debug, Clone, PartialEq)]
+pub (crate) struct LoadedLevel {
+    pub (crate) identifier: String,
+    /// The level's bottom left cell.
+    pub (crate) origin: IVec2,
+    /// Width and height in cells.
+    pub (crate) size: IVec2,
+}
+
+impl LoadedLevel {
+    pub (crate) fn new(level: &Level, layout: LevelLayout) -> Self {
+        let origin = match layout {
+            LevelLayout::Separate => IVec2::ZERO,
+            // LDtk's world y points down and is the level's top edge. Levels
+            // left of or below the origin must round down, not toward zero.
+            LevelLayout::Seamless => IVec2::new(level.world_x, -level.world_y - level.px_hei)
+                .div_euclid(IVec2::splat(GRID_SIZE)),
+        };
+        Self {
+            identifier: level.identifier.clone(),
+            origin,
+            size: IVec2::new(level.px_wid, level.px_hei) / GRID_SIZE,
+        }
+    }
+
+    /// World cell of an LDtk grid position in this level (origin in the top
+    /// left).
+    pub (crate) fn cell_from_ldtk(&self, cell: IVec2) -> GridCoords {
+        GridCoords::new(self.origin.x + cell.x, self.origin.y + self.size.y - 1 - cell.y)
+    }
+
+    pub (crate) fn contains(&self, gc: &GridCoords) -> bool {
+        let cell = IVec2::new(gc.x, gc.y) - self.origin;
+        cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all()
+    }
+
+    /// The area the level covers, in pixels.
+    pub (crate) fn rect(&self) -> Rect {
+        Rect::from_corners(
+            (self.origin * GRID_SIZE).as_vec2(),
+            ((self.origin + self.size) * GRID_SIZE).as_vec2(),
+        )
+    }
+}
+
+/// Every spawned level, keyed by level iid.
+#[derive(Resource, Default, Debug)]
+pub (crate) struct LoadedLevels {
+    pub (crate) levels: HashMap<String, LoadedLevel>,
+}
+
+impl LoadedLevels {
+    /// The level that covers `gc`, with its iid.
+    pub (crate) fn level_at(&self, gc: &GridCoords) -> Option<(&String, &LoadedLevel)> {
+        self.levels.iter().find(|(_, level)| level.contains(gc))
+    }
+}
+
+/// Added to the player once it has been moved out of its level and into the
+/// world, so it survives that level despawning.
+#[derive(Default, Component)]
+pub (crate) struct WorldPlayer;
+
+/// Finds the level an LDtk entity belongs to, and converts its `GridCoords`
+/// to world grid coordinates.
+#[derive(SystemParam)]
+pub (crate) struct WorldCoords<'w, 's> {
+    parents: Query<'w, 's, &'static Parent>,
+    level_handles: Query<'w, 's, &'static Handle<LdtkLevel>>,
+    level_assets: Res<'w, Assets<LdtkLevel>>,
+    loaded: Res<'w, LoadedLevels>,
+}
+
+impl<'w, 's> WorldCoords<'w, 's> {
+    /// Iid of the level `entity` was spawned in.
+    pub (crate) fn level_iid(&self, entity: Entity) -> Option<&str> {
+        let mut current = entity;
+        loop {
+            if let Ok(handle) = self.level_handles.get(current) {
+                return self.level_assets.get(handle).map(|level| level.level.iid.as_str());
+            }
+            current = self.parents.get(current).ok()?.get();
+        }
+    }
+
+    pub (crate) fn to_world(&self, entity: Entity, gc: GridCoords) -> GridCoords {
+        let origin = self
+            .level_iid(entity)
+            .and_then(|iid| self.loaded.levels.get(iid))
+            .map_or(IVec2::ZERO, |level| level.origin);
+        gc + GridCoords::from(origin)
+    }
+}
+
+/// Switches between `LevelLayout`s when the project is loaded or its layout
+/// changes, and respawns the world so levels are placed accordingly.
+pub(crate) fn apply_world_layout(
+    mut asset_events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut layout: ResMut<LevelLayout>,
+    mut settings: ResMut<LdtkSettings>,
+    worlds: Query<Entity, With<Handle<LdtkAsset>>>,
+    world_players: Query<Entity, With<WorldPlayer>>,
+    mut commands: Commands,
+) {
+    for event in asset_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(ldtk_asset) = ldtk_assets.get(handle) else {
+            continue;
+        };
+        let new_layout = LevelLayout::from_world_layout(ldtk_asset.project.world_layout.as_ref());
+        if new_layout == *layout {
+            continue;
+        }
+        info!("Using {:?} level layout", new_layout);
+        *layout = new_layout;
+        settings.level_spawn_behavior = match new_layout {
+            LevelLayout::Separate => LevelSpawnBehavior::UseZeroTranslation,
+            LevelLayout::Seamless => LevelSpawnBehavior::UseWorldTranslation {
+                load_level_neighbors: true,
+            },
+        };
+        // Levels may already have been spawned with the old layout. They
+        // bring a new player, and the respawn leaves an adopted one behind.
+        for world in worlds.iter() {
+            commands.entity(world).insert(Respawn);
+        }
+        for player in world_players.iter() {
+            commands.entity(player).despawn_recursive();
+        }
+    }
+}
+
+pub(crate) fn track_loaded_levels(
+    mut loaded: ResMut<LoadedLevels>,
+    mut level_events: EventReader<LevelEvent>,
+    layout: Res<LevelLayout>,
+    ldtk_project_entities: Query<&Handle<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
+) {
+    for level_event in level_events.iter() {
+        match level_event {
+            LevelEvent::Spawned(level_iid) => {
+                let Some(level) = ldtk_project_entities
+                    .get_single()
+                    .ok()
+                    .and_then(|handle| ldtk_project_assets.get(handle))
+                    .and_then(|ldtk_project| ldtk_project.get_level(&LevelSelection::Iid(level_iid.to_string())))
+                else {
+                    error!("Spawned level {} is not in the loaded project", level_iid);
+                    continue;
+                };
+                // Separate levels all sit at the origin, only the newest one
+                // is there.
+                if *layout == LevelLayout::Separate {
+                    loaded.levels.clear();
+                }
+                loaded.levels.insert(level_iid.clone(), LoadedLevel::new(level, *layout));
+            },
+            LevelEvent::Despawned(level_iid) if *layout == LevelLayout::Seamless => {
+                loaded.levels.remove(level_iid);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// In a seamless world every spawned level brings its own player, and the
+/// player has to outlive the level it was spawned in. The player of the
+/// selected level is moved into the world, all others are despawned.
+pub(crate) fn adopt_player<Player: Component>(
+    layout: Res<LevelLayout>,
+    current_level: Res<CurrentLevel>,
+    new_players: Query<Entity, Added<Player>>,
+    world_players: Query<(), (With<Player>, With<WorldPlayer>)>,
+    worlds: Query<Entity, (With<Handle<LdtkAsset>>, Without<Respawn>)>,
+    transforms: Query<&Transform>,
+    world_coords: WorldCoords,
+    mut commands: Commands,
+) {
+    // Players spawned before the layout changed are in levels that are about
+    // to be respawned.
+    if *layout != LevelLayout::Seamless || layout.is_changed() {
+        return;
+    }
+    let Ok(world) = worlds.get_single() else {
+        return;
+    };
+    let mut adopted = !world_players.is_empty();
+    for player in new_players.iter() {
+        if adopted || world_coords.level_iid(player) != Some(current_level.iid.as_str()) {
+            commands.entity(player).despawn_recursive();
+            continue;
+        }
+        // Sum up the translations of the player, its layer and its level.
+        let mut translation = Vec3::ZERO;
+        let mut current = player;
+        while current != world {
+            translation += transforms.get(current).map_or(Vec3::ZERO, |tf| tf.translation);
+            let Ok(parent) = world_coords.parents.get(current) else {
+                break;
+            };
+            current = parent.get();
+        }
+        commands
+            .entity(player)
+            .insert((WorldPlayer, Transform::from_translation(translation)))
+            .set_parent(world);
+        adopted = true;
+    }
+}
+
+/// Selects the level the player walked into, in a seamless world. It is
+/// already spawned as a neighbour of the level they left, so nothing is
+/// respawned and no transition is needed.
+pub(crate) fn scroll_to_neighbour<Player: Component>(
+    layout: Res<LevelLayout>,
+    loaded: Res<LoadedLevels>,
+    player: Query<&Transform, With<Player>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut bounds: ResMut<LevelBounds>,
+    mut came_from: ResMut<CameFrom>,
+    mut player_entered: EventWriter<PlayerEnteredLevel>,
+) {
+    if *layout != LevelLayout::Seamless {
+        return;
+    }
+    let Ok(tf) = player.get_single() else {
+        return;
+    };
+    let Some((level_iid, level)) = loaded.level_at(&to_grid_coords(*tf)) else {
+        return;
+    };
+    if *level_iid == current_level.iid {
+        return;
+    }
+    info!("Player walked into {}", level.identifier);
+    // Not a change `move_player_to_entrance` should react to, the player
+    // stays where they are.
+    current_level.bypass_change_detection().iid = level_iid.clone();
+    *level_selection = LevelSelection::Iid(level_iid.clone());
+    bounds.rect = level.rect();
+    came_from.from.clear();
+    player_entered.send(PlayerEnteredLevel {
+        level_iid: level_iid.clone(),
+        entrance: None,
+        scrolled: true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_ldtk::ldtk::LdtkJson;
+
+    #[test]
+    fn seamless_levels_sit_at_their_world_position() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/farms.ldtk");
+        let project: LdtkJson = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let level_at = |x, y| project.levels.iter().find(|l| l.world_x == x && l.world_y == y).unwrap();
+        let south = LoadedLevel::new(level_at(0, 0), LevelLayout::Seamless);
+        let north = LoadedLevel::new(level_at(0, -480), LevelLayout::Seamless);
+
+        assert_eq!(north.origin, IVec2::ZERO);
+        assert_eq!(south.origin, IVec2::new(0, -15));
+        assert_eq!(south.cell_from_ldtk(IVec2::ZERO), GridCoords::new(0, -1));
+        assert!(south.contains(&GridCoords::new(0, -1)));
+        assert!(!south.contains(&GridCoords::new(0, 0)));
+        assert!(north.contains(&GridCoords::new(0, 0)));
+        assert_eq!(south.rect().max.y, north.rect().min.y);
+
+        let separate = LoadedLevel::new(level_at(0, 0), LevelLayout::Separate);
+        assert_eq!(separate.origin, IVec2::ZERO);
+    }
+}
@@ -0,0 +1,58 @@
+// File: lib.rs
+//
+// The game's plugins, shared by the game in `main.rs` and the level checking
+// binaries in `bin/`.
+
+// Bevy systems take their resources and queries as arguments.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use bevy::prelude::*;
+
+pub mod animations;
+pub mod player_movement;
+pub mod utils;
+pub mod levels;
+pub mod constants;
+pub mod save;
+pub mod input_map;
+pub mod interaction;
+pub mod hud;
+pub mod camera;
+pub mod display;
+pub mod transition;
+pub mod audio;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    #[default]
+    South,
+    West
+}
+
+impl Direction {
+    /// One grid cell in this direction.
+    pub fn offset(self) -> IVec2 {
+        match self {
+            Self::North => IVec2::Y,
+            Self::East => IVec2::X,
+            Self::South => IVec2::NEG_Y,
+            Self::West => IVec2::NEG_X,
+        }
+    }
+
+    /// The direction `axis` points the most along, or `None` for no input.
+    pub fn from_axis(axis: Vec2) -> Option<Self> {
+        if axis == Vec2::ZERO {
+            None
+        } else if axis.x.abs() >= axis.y.abs() {
+            Some(if axis.x > 0. { Self::East } else { Self::West })
+        } else {
+            Some(if axis.y > 0. { Self::North } else { Self::South })
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Player;
@@ -1,42 +1,35 @@
 
 
 use bevy::{
+    asset::ChangeWatcher,
     core_pipeline::{
         bloom::BloomSettings,
         tonemapping::Tonemapping,
     },
     prelude::*
 };
+use std::time::Duration;
 use bevy_asset_loader::prelude::*;
 // use bevy_ecs_ldtk::prelude::*;
 
-mod animations;
-mod player_movement;
-mod utils;
-mod levels;
-mod constants;
-
-// use crate::levelss::{LevelPlugin, Colliders};
-use crate::animations::{AnimationTimer, Animator};
-use crate::player_movement::{PlayerMover, PlayerFacing, PlayerWantsToMove};
-use crate::levels::prelude::LevelPlugin;
-use crate::constants::GRID_SIZE;
+use pixel_farms::{Direction, Player};
+use pixel_farms::animations::prelude::{AnimationAssets, Animator};
+use pixel_farms::player_movement::prelude::*;
+use pixel_farms::levels::prelude::{LevelPlugin, Colliders};
+use pixel_farms::save::prelude::SavePlugin;
+use pixel_farms::hud::prelude::HudPlugin;
+use pixel_farms::camera::prelude::{CameraPlugin, MainCamera};
+use pixel_farms::display::prelude::DisplayPlugin;
+use pixel_farms::transition::prelude::{not_transitioning, TransitionPlugin};
+use pixel_farms::audio::prelude::SoundPlugin;
+use pixel_farms::input_map::prelude::*;
+use pixel_farms::interaction::prelude::{InteractionPlugin, PlayerWantsToInteract};
 
 const SCREEN_WIDTH: f32 = 640.;
 const SCREEN_HEIGHT: f32 = 480.;
 
 // const GRID_SIZE: i32 = 32;
 
-#[derive(Default, Debug, Clone, Copy)]
-pub enum Direction {
-    North,
-    East,
-    #[default]
-    South,
-    West
-}
-
-
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 enum GameStates {
     #[default]
@@ -50,9 +43,6 @@ impl GameStates {
     }
 }
 
-#[derive(Component, Default)]
-pub struct Player;
-
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins
@@ -66,57 +56,74 @@ fn main() {
                 }
             )
             .set(ImagePlugin::default_nearest())
+            .set(
+                AssetPlugin {
+                    // Hot-reload animation clips and levels.
+                    watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+                    ..default()
+                }
+            )
         )
         // .add_plugins(LdtkPlugin)
+        .add_plugins(InputMapPlugin)
         .add_plugins(Animator)
         .add_plugins(PlayerMover::<Colliders>::default())
-        .add_plugins(LevelPlugin::<Player, AnimationTimer>::default())
+        .add_plugins(LevelPlugin::<Player>::default())
+        .add_plugins(InteractionPlugin::<Player>::default())
+        .add_plugins(SavePlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(CameraPlugin::<Player>::default())
+        .add_plugins(DisplayPlugin)
+        .add_plugins(TransitionPlugin)
+        .add_plugins(SoundPlugin::<Player>::default())
         .add_state::<GameStates>()
         .add_loading_state(
             LoadingState::new(GameStates::AssetLoading).continue_to_state(GameStates::Main)
         )
+        .add_collection_to_loading_state::<_, AnimationAssets>(GameStates::AssetLoading)
         .add_systems(OnEnter(GameStates::main()), setup)
         .add_systems(Update,
             (
                 take_input,
-            ).run_if(in_state(GameStates::Main)))
+            )
+                .run_if(in_state(GameStates::Main))
+                .run_if(not_transitioning))
         .run();
 }
 
 fn take_input(
-    input: Res<Input<KeyCode>>,
-    mut player: Query<&mut TextureAtlasSprite, With<Player>>,
+    actions: Res<ActionState>,
     mut facing: ResMut<PlayerFacing>,
-    mut player_wants_to_move: ResMut<PlayerWantsToMove>
+    mut player_wants_to_move: ResMut<PlayerWantsToMove>,
+    mut movement_input: ResMut<MovementInput>,
+    mut player_wants_to_interact: ResMut<PlayerWantsToInteract>,
 ) {
-    let Ok(mut player) = player.get_single_mut() else {
-        error!("Unable to find player!");
-        return;
-    };
-
-    let movement_keys = vec![KeyCode::W, KeyCode::A, KeyCode::S, KeyCode::D];
-
-    if input.just_pressed(KeyCode::D) {
-        facing.facing = Direction::East;
-        player.flip_x = false;
-    }
-
-    if input.just_pressed(KeyCode::A) {
-        facing.facing = Direction::West;
-        player.flip_x = true;
-    }
-
-    if input.just_pressed(KeyCode::W) {
-        facing.facing = Direction::North;
-        player.flip_x = false;
+    let move_actions = [
+        (Action::MoveRight, Direction::East),
+        (Action::MoveLeft, Direction::West),
+        (Action::MoveUp, Direction::North),
+        (Action::MoveDown, Direction::South),
+    ];
+
+    for (action, direction) in move_actions {
+        if actions.just_pressed(action) {
+            facing.facing = direction;
+        }
     }
 
-    if input.just_pressed(KeyCode::S) {
-        facing.facing = Direction::South;
-        player.flip_x = false;
+    let axis = actions.move_axis();
+    // A stick can move the player without passing the pressed threshold, and
+    // releasing one key of a diagonal leaves the other held: face where the
+    // player is actually going whenever that isn't the way they face.
+    if facing.facing.offset().as_vec2().dot(axis) <= 0. {
+        if let Some(direction) = Direction::from_axis(axis) {
+            facing.facing = direction;
+        }
     }
 
-    *player_wants_to_move = PlayerWantsToMove(input.any_pressed(movement_keys));
+    *movement_input = MovementInput(axis);
+    *player_wants_to_move = PlayerWantsToMove(axis != Vec2::ZERO);
+    *player_wants_to_interact = PlayerWantsToInteract(actions.just_pressed(Action::Interact));
 }
 
 
@@ -137,6 +144,7 @@ fn setup(
             ..default()
         },
         BloomSettings::default(), // 3. Enable bloom for the camera
+        MainCamera,
     ));
 }
 
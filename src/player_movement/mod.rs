@@ -1,33 +1,134 @@
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::{collide, Collision};
+use bevy::utils::HashSet;
+use bevy_ecs_ldtk::GridCoords;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
 
-/// Add this component to the player entity. This is used to
-/// identify the player entity in the query and apply controls
-/// to it.
-#[derive(Component)]
-pub struct PlayerControlled;
+use crate::constants::GRID_SIZE;
+use crate::Direction;
 
-#[derive(Default)]
-pub struct PlayerMovementPlugin;
+mod systems;
+
+use systems::*;
+
+#[derive(Resource, Default, PartialEq, Clone, Copy)]
+pub struct PlayerWantsToMove(pub bool);
+
+/// The direction the player wants to move in. Diagonal input is allowed and
+/// is normalized before the speed is applied.
+#[derive(Resource, Default, PartialEq, Clone, Copy, Debug)]
+pub struct MovementInput(pub Vec2);
+
+/// Sent every frame the player wants to move but can't move at all. Sliding
+/// along a wall doesn't count.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MovementBlocked {
+    pub direction: Direction,
+}
+
+#[derive(Resource, Default, Clone, Copy)]
+pub struct PlayerFacing {
+    pub facing: Direction,
+}
+
+/// How hard a grid cell is to move through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainCost {
+    /// Multiplies `Speed` while moving into the cell. Every `MovementMode`
+    /// samples the terrain the player is moving into: a grid step uses its
+    /// destination cell, free movement the slowest cell the hitbox covers
+    /// after the frame's move.
+    pub speed: f32,
+    /// The cell can only be entered with this in `Abilities`, e.g. water
+    /// needs a boat.
+    pub requires: Option<String>,
+}
+
+impl Default for TerrainCost {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            requires: None,
+        }
+    }
+}
+
+/// What the player can do that lets them into otherwise closed terrain. If
+/// you add this resource to your app, it will override the default of none.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Abilities {
+    abilities: HashSet<String>,
+}
+
+impl Abilities {
+    pub fn grant(&mut self, ability: impl Into<String>) {
+        self.abilities.insert(ability.into());
+    }
+
+    pub fn has(&self, ability: &str) -> bool {
+        self.abilities.contains(ability)
+    }
+}
+
+/// Grid cells overlapped by the rectangle from `min` to `max` (in pixels).
+/// Touching a cell's edge does not count as overlapping it.
+fn cells_in_area(min: Vec2, max: Vec2) -> impl Iterator<Item = GridCoords> {
+    let min_cell = (min / GRID_SIZE as f32).floor().as_ivec2();
+    let max_cell = (max / GRID_SIZE as f32).ceil().as_ivec2() - IVec2::ONE;
+    (min_cell.x..=max_cell.x)
+        .flat_map(move |x| (min_cell.y..=max_cell.y).map(move |y| GridCoords::new(x, y)))
+}
+
+/// Implemented by resources that know which grid cells block movement. Used by
+/// `CollisionBackend::Grid` and by `MovementMode::GridStep`.
+pub trait Collider {
+    fn on_collider(&self, other: &GridCoords) -> bool;
+
+    /// The cost of moving through `cell`, if it differs from plain ground.
+    fn terrain_cost(&self, _cell: &GridCoords) -> Option<&TerrainCost> {
+        None
+    }
+
+    /// Whether any cell overlapped by the rectangle from `min` to `max` (in
+    /// pixels) is a collider. Touching a cell's edge does not count as
+    /// overlapping it.
+    fn blocks_area(&self, min: Vec2, max: Vec2) -> bool {
+        cells_in_area(min, max).any(|cell| self.on_collider(&cell))
+    }
+
+    /// Whether the player can't enter `cell`, either because it is a collider
+    /// or because its terrain requires something they lack.
+    fn blocks(&self, cell: &GridCoords, abilities: &Abilities) -> bool {
+        self.on_collider(cell)
+            || self
+                .terrain_cost(cell)
+                .and_then(|cost| cost.requires.as_deref())
+                .is_some_and(|ability| !abilities.has(ability))
+    }
+
+    /// Like `blocks_area`, but also checks what the terrain requires.
+    fn blocks_area_for(&self, min: Vec2, max: Vec2, abilities: &Abilities) -> bool {
+        cells_in_area(min, max).any(|cell| self.blocks(&cell, abilities))
+    }
+
+    /// Speed multiplier of the slowest cell overlapped by the rectangle from
+    /// `min` to `max`.
+    fn speed_in_area(&self, min: Vec2, max: Vec2) -> f32 {
+        cells_in_area(min, max)
+            .filter_map(|cell| self.terrain_cost(&cell).map(|cost| cost.speed))
+            .fold(1., f32::min)
+    }
+}
 
 #[derive(Component)]
-/// Represents a collider used for collision detection. Set the
+/// Represents a collider used by `CollisionBackend::Aabb`. Set the
 /// size to the size of the sprite. Must be rectangular. The
 /// origin of the sprite is assumed to be in the center.
-pub struct Collider {
+pub struct AabbCollider {
     pub size: Vec2,
 }
 
-#[derive(Resource, Default, Debug, Clone)]
-struct PlayerMovement {
-    // wished movement of the player. This is not the actual movement
-    // but will be used to check for collisions. If there is no collision
-    // the player will be moved. we assume a 2d movement for now.
-    player_movement: Vec2,
-    has_moved: bool,
-}
-
-/// The speed of the player in some kind of arbitrary units. If you add
+/// The speed of the player in pixels per second. If you add
 /// this resource to your app, it will override the default speed of 100.
 #[derive(Resource)]
 pub struct Speed(pub f32);
@@ -36,135 +137,224 @@ impl Default for Speed {
     fn default() -> Self {
         Self(100.)
     }
+}
 
+/// The part of the player that collides with the world, relative to the
+/// centre of the sprite. If you add this resource to your app, it will
+/// override the default, which covers the feet of the 32px sprite.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PlayerHitbox {
+    pub size: Vec2,
+    pub offset: Vec2,
 }
 
-fn take_input(
-    mut player_movement: ResMut<PlayerMovement>,
-    speed: Res<Speed>,
-    input: Res<Input<KeyCode>>,
-    time: Res<Time>,
-) {
-    let left_button = KeyCode::A;
-    let right_button = KeyCode::D;
-    let up_button = KeyCode::W;
-    let down_button = KeyCode::S;
-    let x_sign = {
-        if input.pressed(left_button) {
-            -1.
-        } else if input.pressed(right_button) {
-            1.
-        } else {
-            0.
-        }
-    };
-    let x_movement = x_sign * speed.0 * time.delta_seconds();
-    let y_sign = {
-        if input.pressed(up_button) {
-            1.
-        } else if input.pressed(down_button) {
-            -1.
-        } else {
-            0.
+impl Default for PlayerHitbox {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(20., 12.),
+            offset: Vec2::new(0., -10.),
         }
-    };
+    }
+}
 
-    let y_movement = y_sign * speed.0 * time.delta_seconds();
+impl PlayerHitbox {
+    /// Corners of the hitbox when the sprite is centred on `position`.
+    pub fn bounds_at(&self, position: Vec2) -> (Vec2, Vec2) {
+        let centre = position + self.offset;
+        (centre - self.size / 2., centre + self.size / 2.)
+    }
+}
 
-    *player_movement = PlayerMovement {
-        player_movement: Vec2::new(
-            x_movement,
-            y_movement,
-        ),
-        has_moved: false,
-    };
+/// How `PlayerMover` turns input into movement.
+#[derive(Resource, Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MovementMode {
+    /// Move continuously while input is held.
+    #[default]
+    Free,
+    /// Slide exactly one grid cell per step, buffering the next direction
+    /// while a step is in progress. Always collides against the grid.
+    GridStep,
 }
 
-fn check_for_collisions(
-    player_controlled_query: Query<(&Transform, Option<&Collider>), With<PlayerControlled>>,
-    colliders: Query<(&Transform, &Collider), Without<PlayerControlled>>,
-    mut player_movement: ResMut<PlayerMovement>,
-) {
-    // let mut collided = vec![];
-    let Ok(
-        (&Transform{translation: player_translation,..}, maybe_player_collider)
-    ) = player_controlled_query.get_single() else { return;};
-    let Some(&Collider {size: player_size}) = maybe_player_collider else {return;};
-    let next_position = player_translation + Vec3::new(player_movement.player_movement.x, player_movement.player_movement.y, 0.);
-    colliders
-        .iter()
-        .map(|(tf, collider)| {
-            collide(
-                next_position,
-                player_size,
-                tf.translation,
-                collider.size,
-            )
-        })
-        .flatten()
-        .for_each(|collision| deal_with_collision(collision, &mut player_movement));
-
-}
-
-fn deal_with_collision(
-    collided: Collision,
-    player_movement: &mut PlayerMovement,
-) {
-    match collided {
-        Collision::Right => {
-            if player_movement.player_movement.x < 0. {
-                player_movement.player_movement.x = 0.;
-            }
-        },
-        Collision::Left => {
-            if player_movement.player_movement.x > 0. {
-                player_movement.player_movement.x = 0.;
-
-            }
-        },
-        Collision::Bottom => {
-            if player_movement.player_movement.y > 0. {
-                player_movement.player_movement.y = 0.;
-            }
-        },
-        Collision::Top => {
-            if player_movement.player_movement.y < 0. {
-                player_movement.player_movement.y = 0.;
-            }
-        },
-        _ => {}
-
-    }
-}
-
-fn move_player(
-    mut player_controlled_query: Query<&mut Transform, With<PlayerControlled>>,
-    mut player_movement: ResMut<PlayerMovement>,
-) {
-    let Ok(mut player) = player_controlled_query.get_single_mut() else {return;};
-    if !player_movement.has_moved {
-        player.translation += Vec3::new(player_movement.player_movement.x, player_movement.player_movement.y, 0.);
-        player_movement.has_moved = true;
-    }
-}
-
-impl Plugin for PlayerMovementPlugin {
+/// What the player collides with in `MovementMode::Free`.
+#[derive(Resource, Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CollisionBackend {
+    /// Test every cell overlapped by the `PlayerHitbox` against the
+    /// `Collider` resource, resolving each axis separately so the player
+    /// slides along walls.
+    #[default]
+    Grid,
+    /// Test the player's `AabbCollider` (or the `PlayerHitbox` if it has none)
+    /// against every other `AabbCollider`.
+    Aabb,
+}
+
+/// The step currently being taken in `MovementMode::GridStep`.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct GridStep {
+    from: Vec3,
+    to: Vec3,
+    /// Seconds the step takes, slower when stepping into costly terrain.
+    duration: f32,
+    progress: f32,
+    in_progress: bool,
+    buffered: Option<Direction>,
+}
+
+/// Moves the entity with the `Player` component according to `MovementInput`
+/// and `PlayerFacing`. `U` is the grid collider resource, which also provides
+/// the `TerrainCost` of each cell.
+#[derive(Default)]
+pub struct PlayerMover<U: Collider + Resource> {
+    collider: PhantomData<U>,
+    mode: MovementMode,
+    backend: CollisionBackend,
+}
+
+impl<U> PlayerMover<U> where
+    U: Collider + Resource {
+    pub fn with_mode(mut self, mode: MovementMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: CollisionBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+}
+
+impl<U> Plugin for PlayerMover<U>
+    where U: Collider + Resource {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(PlayerMovement::default())
-            .insert_resource(Speed::default())
+            .insert_resource(PlayerFacing::default())
+            .insert_resource(PlayerWantsToMove::default())
+            .insert_resource(MovementInput::default())
+            .insert_resource(GridStep::default())
+            .insert_resource(self.mode)
+            .insert_resource(self.backend)
+            .init_resource::<Speed>()
+            .init_resource::<PlayerHitbox>()
+            .init_resource::<Abilities>()
+            .add_event::<MovementBlocked>()
             .add_systems(
                 Update,
                 (
-                    take_input,
-                    check_for_collisions,
-                    move_player,
-                ).chain()
+                    move_player::<U>
+                        .run_if(resource_equals(CollisionBackend::Grid)),
+                    move_player_aabb::<U>
+                        .run_if(resource_equals(CollisionBackend::Aabb)),
+                )
+                    .run_if(resource_exists_and_equals(PlayerWantsToMove(true)))
+                    .run_if(resource_equals(MovementMode::Free))
+            )
+            .add_systems(
+                Update,
+                step_player::<U>
+                    .run_if(resource_equals(MovementMode::GridStep))
             )
             ;
     }
 }
 
 pub mod prelude {
-    pub use super::{PlayerControlled, PlayerMovementPlugin, Collider, Speed};
-}
\ No newline at end of file
+    pub use super::{
+        AabbCollider,
+        Abilities,
+        Collider,
+        CollisionBackend,
+        MovementBlocked,
+        MovementInput,
+        MovementMode,
+        PlayerFacing,
+        PlayerHitbox,
+        PlayerMover,
+        PlayerWantsToMove,
+        Speed,
+        TerrainCost,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::HashSet;
+
+    struct Cells(HashSet<GridCoords>);
+
+    impl Collider for Cells {
+        fn on_collider(&self, other: &GridCoords) -> bool {
+            self.0.contains(other)
+        }
+    }
+
+    struct Water(TerrainCost);
+
+    impl Collider for Water {
+        fn on_collider(&self, _: &GridCoords) -> bool {
+            false
+        }
+
+        fn terrain_cost(&self, cell: &GridCoords) -> Option<&TerrainCost> {
+            (cell.x == 1).then_some(&self.0)
+        }
+    }
+
+    #[test]
+    fn blocks_area_checks_every_overlapped_cell() {
+        let cells = Cells([GridCoords::new(1, 0)].into_iter().collect());
+        // Straddles cells (0, 0) and (1, 0).
+        assert!(cells.blocks_area(Vec2::new(20., 4.), Vec2::new(40., 16.)));
+        // Entirely inside cell (0, 0).
+        assert!(!cells.blocks_area(Vec2::new(4., 4.), Vec2::new(28., 28.)));
+        // Touches the edge of cell (1, 0) without overlapping it.
+        assert!(!cells.blocks_area(Vec2::new(12., 4.), Vec2::new(32., 16.)));
+    }
+
+    #[test]
+    fn terrain_needs_its_ability_and_slows_the_player() {
+        let water = Water(TerrainCost {
+            speed: 0.5,
+            requires: Some("Boat".to_string()),
+        });
+        let mut abilities = Abilities::default();
+        let (min, max) = (Vec2::new(20., 4.), Vec2::new(40., 16.));
+        assert!(!water.blocks_area(min, max));
+        assert!(water.blocks_area_for(min, max, &abilities));
+        abilities.grant("Boat");
+        assert!(!water.blocks_area_for(min, max, &abilities));
+
+        assert_eq!(water.speed_in_area(min, max), 0.5);
+        assert_eq!(water.speed_in_area(Vec2::new(4., 4.), Vec2::new(28., 28.)), 1.);
+    }
+
+    #[test]
+    fn blocked_terrain_is_resolved_per_axis() {
+        let water = Water(TerrainCost {
+            speed: 0.5,
+            requires: Some("Boat".to_string()),
+        });
+        let hitbox = PlayerHitbox::default();
+        // The hitbox spans x 2..22 in the column left of the water.
+        let position = Vec2::new(12., 48.);
+        let next = resolve_terrain(position, Vec2::new(20., 5.), &hitbox, &water, &Abilities::default());
+        // Flush against the water, and still moving up along it.
+        assert_eq!(next, Vec2::new(22., 53.));
+    }
+
+    #[test]
+    fn analog_input_keeps_its_magnitude() {
+        let speed = Speed(100.);
+        let mut time = Time::default();
+        let start = time.startup();
+        // The first update only starts the clock.
+        time.update_with_instant(start);
+        time.update_with_instant(start + std::time::Duration::from_secs(1));
+
+        let half = frame_movement(&MovementInput(Vec2::new(0.5, 0.)), &speed, &time);
+        assert!((half - Vec2::new(50., 0.)).length() < 1e-3);
+        let diagonal = frame_movement(&MovementInput(Vec2::ONE), &speed, &time);
+        assert!((diagonal.length() - 100.).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::{collide, Collision};
+use bevy_ecs_ldtk::utils::grid_coords_to_translation;
+
+use crate::constants::{GRID_SIZE, GRID_SIZE_IVEC};
+use crate::player_movement::*;
+use crate::utils::translation_to_grid_coords;
+use crate::Player;
+
+/// This frame's movement. Input is clamped to length 1 so that diagonals are
+/// no faster than moving along an axis, while a half-pushed stick still walks
+/// at half speed.
+pub(crate) fn frame_movement(input: &MovementInput, speed: &Speed, time: &Time) -> Vec2 {
+    input.0.clamp_length_max(1.) * speed.0 * time.delta_seconds()
+}
+
+/// How much of `movement` along one axis the span from `min` to `max` can
+/// make before it crosses into the next grid cell.
+fn clamp_to_grid_edge(min: f32, max: f32, movement: f32) -> f32 {
+    let grid = GRID_SIZE as f32;
+    if movement > 0. {
+        (((max + movement) / grid).floor() * grid - max).clamp(0., movement)
+    } else if movement < 0. {
+        (((min + movement) / grid).ceil() * grid - min).clamp(movement, 0.)
+    } else {
+        0.
+    }
+}
+
+/// Speed multiplier for moving from `position` by `movement`. Like
+/// `step_player`, this samples the terrain being moved into: the cells the
+/// hitbox covers once the move is done.
+fn terrain_speed<U: Collider>(position: Vec2, movement: Vec2, hitbox: &PlayerHitbox, colliders: &U) -> f32 {
+    let (min, max) = hitbox.bounds_at(position + movement);
+    colliders.speed_in_area(min, max)
+}
+
+/// Where the player ends up moving from `position` by `movement` through the
+/// terrain. Each axis is resolved on its own, so that a blocked axis doesn't
+/// stop movement along the other one and the player slides along walls.
+pub(crate) fn resolve_terrain<U: Collider>(
+    mut position: Vec2,
+    movement: Vec2,
+    hitbox: &PlayerHitbox,
+    colliders: &U,
+    abilities: &Abilities,
+) -> Vec2 {
+    for axis_movement in [Vec2::new(movement.x, 0.), Vec2::new(0., movement.y)] {
+        if axis_movement == Vec2::ZERO {
+            continue;
+        }
+        let (min, max) = hitbox.bounds_at(position + axis_movement);
+        if !colliders.blocks_area_for(min, max, abilities) {
+            position += axis_movement;
+            continue;
+        }
+        // Close the gap to the wall instead of stopping short of it.
+        let (min, max) = hitbox.bounds_at(position);
+        let clamped = Vec2::new(
+            clamp_to_grid_edge(min.x, max.x, axis_movement.x),
+            clamp_to_grid_edge(min.y, max.y, axis_movement.y),
+        );
+        let (min, max) = hitbox.bounds_at(position + clamped);
+        if !colliders.blocks_area_for(min, max, abilities) {
+            position += clamped;
+        }
+    }
+    position
+}
+
+pub(crate) fn move_player<U: Collider + Resource>(
+    mut player: Query<&mut Transform, With<Player>>,
+    input: Res<MovementInput>,
+    speed: Res<Speed>,
+    hitbox: Res<PlayerHitbox>,
+    abilities: Res<Abilities>,
+    time: Res<Time>,
+    colliders: Res<U>,
+    facing: Res<PlayerFacing>,
+    mut blocked: EventWriter<MovementBlocked>,
+) {
+    let Ok(mut player) = player.get_single_mut() else {
+        error!("No player found!!");
+        return;
+    };
+    let position = player.translation.truncate();
+    let movement = frame_movement(&input, &speed, &time);
+    let movement = movement * terrain_speed(position, movement, &hitbox, colliders.as_ref());
+    let next_position = resolve_terrain(position, movement, &hitbox, colliders.as_ref(), &abilities);
+    if movement != Vec2::ZERO && next_position == position {
+        blocked.send(MovementBlocked { direction: facing.facing });
+    }
+    player.translation = next_position.extend(player.translation.z);
+}
+
+/// Terrain still comes from the grid collider `U`, resolved per axis like in
+/// `move_player`. Only collisions with other entities use their
+/// `AabbCollider`.
+pub(crate) fn move_player_aabb<U: Collider + Resource>(
+    mut player: Query<(&mut Transform, Option<&AabbCollider>), With<Player>>,
+    colliders: Query<(&Transform, &AabbCollider), Without<Player>>,
+    input: Res<MovementInput>,
+    speed: Res<Speed>,
+    hitbox: Res<PlayerHitbox>,
+    abilities: Res<Abilities>,
+    time: Res<Time>,
+    terrain: Res<U>,
+    facing: Res<PlayerFacing>,
+    mut blocked: EventWriter<MovementBlocked>,
+) {
+    let Ok((mut player, player_collider)) = player.get_single_mut() else {
+        error!("No player found!!");
+        return;
+    };
+    let (player_size, player_offset) = player_collider
+        .map_or((hitbox.size, hitbox.offset), |c| (c.size, Vec2::ZERO));
+    let position = player.translation.truncate();
+    let wanted = frame_movement(&input, &speed, &time);
+    let wanted = wanted * terrain_speed(position, wanted, &hitbox, terrain.as_ref());
+    let mut movement = resolve_terrain(position, wanted, &hitbox, terrain.as_ref(), &abilities) - position;
+    let next_position = player.translation + (movement + player_offset).extend(0.);
+    colliders
+        .iter()
+        .filter_map(|(tf, collider)| {
+            collide(
+                next_position,
+                player_size,
+                tf.translation,
+                collider.size,
+            )
+        })
+        .for_each(|collision| deal_with_collision(collision, &mut movement));
+    if wanted != Vec2::ZERO && movement == Vec2::ZERO {
+        blocked.send(MovementBlocked { direction: facing.facing });
+    }
+    player.translation += movement.extend(0.);
+}
+
+fn deal_with_collision(
+    collided: Collision,
+    movement: &mut Vec2,
+) {
+    match collided {
+        Collision::Right => {
+            if movement.x < 0. {
+                movement.x = 0.;
+            }
+        },
+        Collision::Left => {
+            if movement.x > 0. {
+                movement.x = 0.;
+            }
+        },
+        Collision::Bottom => {
+            if movement.y > 0. {
+                movement.y = 0.;
+            }
+        },
+        Collision::Top => {
+            if movement.y < 0. {
+                movement.y = 0.;
+            }
+        },
+        Collision::Inside => {}
+    }
+}
+
+pub(crate) fn step_player<U: Collider + Resource>(
+    mut player: Query<&mut Transform, With<Player>>,
+    facing: Res<PlayerFacing>,
+    wants_to_move: Res<PlayerWantsToMove>,
+    speed: Res<Speed>,
+    abilities: Res<Abilities>,
+    time: Res<Time>,
+    colliders: Res<U>,
+    mut step: ResMut<GridStep>,
+    mut blocked: EventWriter<MovementBlocked>,
+) {
+    let Ok(mut player) = player.get_single_mut() else {
+        error!("No player found!!");
+        return;
+    };
+
+    if step.in_progress {
+        // `PlayerFacing` only changes when a direction is pressed, so a tap
+        // during the step is remembered while a held key is not. Whether the
+        // key is still held is checked once the step is done.
+        if facing.is_changed() {
+            step.buffered = Some(facing.facing);
+        }
+        step.progress = (step.progress + time.delta_seconds() / step.duration).min(1.);
+        if step.progress < 1. {
+            player.translation = step.from.lerp(step.to, step.progress);
+            return;
+        }
+        player.translation = step.to;
+        step.in_progress = false;
+    }
+
+    let next_direction = step.buffered.take().or(wants_to_move.0.then_some(facing.facing));
+    let Some(direction) = next_direction else {
+        return;
+    };
+    let current = translation_to_grid_coords(player.translation.truncate());
+    let destination = current + GridCoords::from(direction.offset());
+    if colliders.blocks(&destination, &abilities) {
+        // Snap onto the current cell so a blocked step never leaves the
+        // player straddling two cells.
+        player.translation = grid_coords_to_translation(current, GRID_SIZE_IVEC)
+            .extend(player.translation.z);
+        blocked.send(MovementBlocked { direction });
+        return;
+    }
+    // Like the free backends, the speed comes from the terrain moved into.
+    let terrain_speed = colliders.terrain_cost(&destination).map_or(1., |cost| cost.speed);
+    *step = GridStep {
+        from: player.translation,
+        to: grid_coords_to_translation(destination, GRID_SIZE_IVEC).extend(player.translation.z),
+        duration: GRID_SIZE as f32 / (speed.0 * terrain_speed),
+        progress: 0.,
+        in_progress: true,
+        buffered: None,
+    };
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::levels::prelude::*;
+use crate::player_movement::Abilities;
+
+/// Bump this whenever `SaveGame` changes shape. Saves with a different version
+/// are rejected rather than half-loaded.
+pub (crate) const SAVE_VERSION: u32 = 5;
+
+pub (crate) const SAVE_DIRECTORY: &str = "saves";
+
+/// Slots `0..SLOT_COUNT` can be selected with `Action::SelectSlot`.
+pub (crate) const SLOT_COUNT: u8 = 4;
+
+/// The slot used by quicksave, quickload and autosave.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveSlot(pub u8);
+
+impl SaveSlot {
+    pub (crate) fn path(&self) -> PathBuf {
+        PathBuf::from(SAVE_DIRECTORY).join(format!("slot_{}.ron", self.0))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub (crate) struct SaveGame {
+    pub (crate) version: u32,
+    pub (crate) level_iid: String,
+    pub (crate) entrance: String,
+    pub (crate) carried_keys: CarriedKeys,
+    pub (crate) level_states: LevelStates,
+    pub (crate) abilities: Abilities,
+}
+
+impl SaveGame {
+    pub (crate) fn from_progress(
+        level_iid: &str,
+        entrance: &str,
+        carried_keys: &CarriedKeys,
+        level_states: &LevelStates,
+        abilities: &Abilities,
+    ) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            level_iid: level_iid.to_string(),
+            entrance: entrance.to_string(),
+            carried_keys: carried_keys.clone(),
+            level_states: level_states.clone(),
+            abilities: abilities.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub (crate) enum SaveError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Deserialize(ron::error::SpannedError),
+    Version { found: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not access save file: {}", e),
+            Self::Serialize(e) => write!(f, "could not write save: {}", e),
+            Self::Deserialize(e) => write!(f, "save file is corrupt: {}", e),
+            Self::Version { found } => write!(
+                f,
+                "save file has version {}, expected {}",
+                found, SAVE_VERSION
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(e: ron::Error) -> Self {
+        Self::Serialize(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        Self::Deserialize(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_ldtk::prelude::*;
+
+    #[test]
+    fn save_round_trips_through_ron() {
+        let mut level_states = LevelStates::default();
+        level_states.record_despawned("level", &EntityIid::new("lock".to_string()));
+        let mut carried_keys = CarriedKeys::default();
+        carried_keys.add(LockKeyColor::new("Blue"));
+        let mut abilities = Abilities::default();
+        abilities.grant("Boat");
+        let save = SaveGame::from_progress("level", "East", &carried_keys, &level_states, &abilities);
+
+        let serialized = ron::to_string(&save).unwrap();
+        let restored: SaveGame = ron::from_str(&serialized).unwrap();
+
+        assert_eq!(restored, save);
+        assert_eq!(restored.level_states, level_states);
+        assert_eq!(restored.carried_keys, carried_keys);
+        assert_eq!(restored.abilities, abilities);
+    }
+}
@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::save::plugin::SavePlugin;
+    pub use crate::save::components::SaveSlot;
+}
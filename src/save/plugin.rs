@@ -0,0 +1,37 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+
+use crate::levels::prelude::GameplaySet;
+use crate::save::{
+    components::*,
+    systems::*,
+};
+
+/// Persists carried keys, abilities, the world state of every visited level
+/// and the current level to `saves/slot_<n>.ron`. Restores the selected slot on
+/// start, autosaves whenever the player steps on a `SwitchLevel`, and selects
+/// slots, quicksaves and quickloads on the matching `Action`s (F1-F4, F5 and F9
+/// by default).
+///
+/// Must be added after `LevelPlugin` and `InputMapPlugin`.
+#[derive(Default)]
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(SaveSlot::default())
+            .add_systems(Startup, load_on_start)
+            .add_systems(
+                Update,
+                (
+                    select_slot,
+                    autosave.after(GameplaySet::Switch),
+                    quicksave.after(select_slot),
+                    quickload.after(select_slot),
+                )
+            )
+            ;
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::fs;
+
+use crate::input_map::prelude::*;
+use crate::levels::prelude::*;
+use crate::player_movement::Abilities;
+use crate::save::components::*;
+
+pub (crate) fn write_save(slot: SaveSlot, save: &SaveGame) -> Result<(), SaveError> {
+    let path = slot.path();
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let contents = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())?;
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Reads the save in `slot`. Returns `Ok(None)` if the slot is empty.
+pub (crate) fn read_save(slot: SaveSlot) -> Result<Option<SaveGame>, SaveError> {
+    let path = slot.path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let save: SaveGame = ron::from_str(&fs::read_to_string(path)?)?;
+    if save.version != SAVE_VERSION {
+        return Err(SaveError::Version { found: save.version });
+    }
+    Ok(Some(save))
+}
+
+fn apply_save(save: &SaveGame, commands: &mut Commands) {
+    commands.insert_resource(LevelSelection::Iid(save.level_iid.clone()));
+    commands.insert_resource(CameFrom { from: save.entrance.clone() });
+    commands.insert_resource(save.carried_keys.clone());
+    commands.insert_resource(save.level_states.clone());
+    commands.insert_resource(save.abilities.clone());
+}
+
+pub (crate) fn load_on_start(
+    slot: Res<SaveSlot>,
+    mut commands: Commands,
+) {
+    match read_save(*slot) {
+        Ok(Some(save)) => {
+            info!("Restoring save from slot {}", slot.0);
+            apply_save(&save, &mut commands);
+        },
+        Ok(None) => info!("No save in slot {}, starting a new game", slot.0),
+        Err(e) => error!("Could not load slot {}: {}", slot.0, e),
+    }
+}
+
+/// Saves on every `SwitchLevel` the player steps on, as if they had already
+/// arrived at the entrance they are heading to. A save that is the same as the
+/// last autosave isn't written again.
+pub (crate) fn autosave(
+    slot: Res<SaveSlot>,
+    mut last_save: Local<Option<(SaveSlot, SaveGame)>>,
+    mut switch_requested: EventReader<LevelSwitchRequested>,
+    ldtk_project_entities: Query<&Handle<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
+    carried_keys: Res<CarriedKeys>,
+    level_states: Res<LevelStates>,
+    abilities: Res<Abilities>,
+) {
+    for request in switch_requested.iter() {
+        let level = ldtk_project_entities
+            .get_single()
+            .ok()
+            .and_then(|handle| ldtk_project_assets.get(handle))
+            .and_then(|ldtk_project| ldtk_project.get_level(&LevelSelection::Identifier(request.to.clone())));
+        let Some(level) = level else {
+            error!("Not autosaving, level {} does not exist", request.to);
+            continue;
+        };
+        let save = SaveGame::from_progress(&level.iid, &request.entrance, &carried_keys, &level_states, &abilities);
+        if last_save.as_ref().is_some_and(|last| *last == (*slot, save.clone())) {
+            continue;
+        }
+        match write_save(*slot, &save) {
+            Ok(()) => debug!("Autosaved to slot {}", slot.0),
+            Err(e) => error!("Autosave to slot {} failed: {}", slot.0, e),
+        }
+        *last_save = Some((*slot, save));
+    }
+}
+
+pub (crate) fn select_slot(
+    actions: Res<ActionState>,
+    mut slot: ResMut<SaveSlot>,
+) {
+    for i in 0..SLOT_COUNT {
+        if actions.just_pressed(Action::SelectSlot(i)) {
+            *slot = SaveSlot(i);
+            info!("Selected save slot {}", i);
+        }
+    }
+}
+
+pub (crate) fn quicksave(
+    actions: Res<ActionState>,
+    slot: Res<SaveSlot>,
+    current_level: Res<CurrentLevel>,
+    came_from: Res<CameFrom>,
+    carried_keys: Res<CarriedKeys>,
+    level_states: Res<LevelStates>,
+    abilities: Res<Abilities>,
+) {
+    if !actions.just_pressed(Action::QuickSave) {
+        return;
+    }
+    let save = SaveGame::from_progress(&current_level.iid, &came_from.from, &carried_keys, &level_states, &abilities);
+    match write_save(*slot, &save) {
+        Ok(()) => info!("Saved to slot {}", slot.0),
+        Err(e) => error!("Saving to slot {} failed: {}", slot.0, e),
+    }
+}
+
+pub (crate) fn quickload(
+    actions: Res<ActionState>,
+    slot: Res<SaveSlot>,
+    worlds: Query<Entity, With<Handle<LdtkAsset>>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::QuickLoad) {
+        return;
+    }
+    match read_save(*slot) {
+        Ok(Some(save)) => {
+            apply_save(&save, &mut commands);
+            // Respawn so that the restored level states are applied to the level
+            // even when the save is for the level we are already in.
+            for world in worlds.iter() {
+                commands.entity(world).insert(Respawn);
+            }
+            info!("Loaded slot {}", slot.0);
+        },
+        Ok(None) => warn!("Slot {} is empty", slot.0),
+        Err(e) => error!("Could not load slot {}: {}", slot.0, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_a_save_restores_progress() {
+        // A slot the game doesn't offer, so no real save is overwritten.
+        let slot = SaveSlot(u8::MAX);
+        let mut level_states = LevelStates::default();
+        level_states.record_despawned("level", &EntityIid::new("lock".to_string()));
+        let mut carried_keys = CarriedKeys::default();
+        carried_keys.add(LockKeyColor::new("Blue"));
+        let mut abilities = Abilities::default();
+        abilities.grant("Boat");
+        write_save(slot, &SaveGame::from_progress("level", "East", &carried_keys, &level_states, &abilities)).unwrap();
+
+        let mut app = App::new();
+        app
+            .insert_resource(slot)
+            .insert_resource(LevelSelection::Index(0))
+            .init_resource::<CarriedKeys>()
+            .init_resource::<LevelStates>()
+            .init_resource::<Abilities>()
+            .add_systems(Startup, load_on_start);
+        app.update();
+        fs::remove_file(slot.path()).unwrap();
+
+        assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::Iid("level".to_string()));
+        assert_eq!(app.world.resource::<CameFrom>().from, "East");
+        assert_eq!(*app.world.resource::<CarriedKeys>(), carried_keys);
+        assert_eq!(*app.world.resource::<LevelStates>(), level_states);
+        assert_eq!(*app.world.resource::<Abilities>(), abilities);
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::Direction;
+
+/// How the screen is covered while the level switches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransitionStyle {
+    /// Fade to black and back.
+    #[default]
+    Fade,
+    /// A black panel slides over the screen in the direction the player is
+    /// walking, then slides off the other side.
+    Slide,
+    /// A black border closes in on the centre of the screen and opens again.
+    Iris,
+}
+
+/// If you add this resource to your app, it will override the defaults.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TransitionSettings {
+    pub style: TransitionStyle,
+    /// Seconds it takes to cover the screen, and again to uncover it.
+    pub duration: f32,
+    /// Seconds to wait for the new level before uncovering the screen
+    /// anyway, so that a level that never arrives doesn't leave it black.
+    pub loading_timeout: f32,
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        Self {
+            style: TransitionStyle::default(),
+            duration: 0.3,
+            loading_timeout: 5.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransitionPhase {
+    #[default]
+    Idle,
+    /// The screen is being covered. The level switch waits until it is done.
+    Covering,
+    /// The screen is covered, waiting for the new level to be in place.
+    Loading,
+    /// The screen is being uncovered.
+    Revealing,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Transition {
+    pub (crate) phase: TransitionPhase,
+    pub (crate) timer: Timer,
+    /// The direction the player was walking when the transition started.
+    pub (crate) direction: Direction,
+}
+
+impl Transition {
+    pub fn phase(&self) -> TransitionPhase {
+        self.phase
+    }
+
+    /// How much of the screen is covered, from 0 to 1.
+    pub (crate) fn coverage(&self) -> f32 {
+        match self.phase {
+            TransitionPhase::Idle => 0.,
+            TransitionPhase::Covering => self.timer.percent(),
+            TransitionPhase::Loading => 1.,
+            TransitionPhase::Revealing => self.timer.percent_left(),
+        }
+    }
+}
+
+/// The full screen node the cover is drawn in.
+#[derive(Component)]
+pub (crate) struct TransitionOverlay;
+
+/// One of the black panels of the cover.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub (crate) enum CoverPanel {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl CoverPanel {
+    pub (crate) const ALL: [CoverPanel; 4] = [Self::Top, Self::Bottom, Self::Left, Self::Right];
+}
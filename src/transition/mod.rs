@@ -0,0 +1,8 @@
+mod components;
+mod systems;
+mod plugin;
+
+pub mod prelude {
+    pub use crate::transition::plugin::{not_transitioning, TransitionPlugin};
+    pub use crate::transition::components::{Transition, TransitionPhase, TransitionSettings, TransitionStyle};
+}
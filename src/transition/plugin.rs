@@ -0,0 +1,44 @@
+// File: plugin.rs
+
+use bevy::prelude::*;
+
+use crate::levels::prelude::GameplaySet;
+use crate::transition::{
+    components::*,
+    systems::*,
+};
+
+/// Covers the screen while the level switches, so the player never sees the
+/// old level disappear or themselves being moved to the entrance. The switch
+/// is held back with the `LevelSwitchGate` until the screen is covered, and
+/// the screen is uncovered once the player is in the new level, or after
+/// `TransitionSettings::loading_timeout` if they never arrive. Player input
+/// should be ignored while `not_transitioning` is false.
+#[derive(Default)]
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TransitionSettings>()
+            .insert_resource(Transition::default())
+            .add_systems(Startup, spawn_overlay)
+            .add_systems(
+                Update,
+                (
+                    start_transition,
+                    advance_transition,
+                    draw_cover,
+                )
+                    .chain()
+                    .after(GameplaySet::Switch)
+                    .before(GameplaySet::ApplySwitch)
+            )
+            ;
+    }
+}
+
+/// Run condition that is false while a transition is covering the screen.
+pub fn not_transitioning(transition: Option<Res<Transition>>) -> bool {
+    transition.is_none_or(|t| t.phase == TransitionPhase::Idle)
+}
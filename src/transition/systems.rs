@@ -0,0 +1,285 @@
+use bevy::prelude::*;
+
+use crate::interaction::prelude::PlayerWantsToInteract;
+use crate::levels::prelude::{LevelSwitchGate, LevelSwitchRequested, PlayerEnteredLevel};
+use crate::player_movement::prelude::*;
+use crate::transition::components::*;
+
+pub (crate) fn spawn_overlay(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            TransitionOverlay,
+        ))
+        .with_children(|parent| {
+            for panel in CoverPanel::ALL {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        background_color: Color::BLACK.into(),
+                        ..default()
+                    },
+                    panel,
+                ));
+            }
+        });
+}
+
+/// Starts covering the screen when a level switch is requested, holding the
+/// switch back until the screen is covered. Input is released so the player
+/// stops where they are.
+pub (crate) fn start_transition(
+    mut switch_requested: EventReader<LevelSwitchRequested>,
+    settings: Res<TransitionSettings>,
+    facing: Res<PlayerFacing>,
+    mut transition: ResMut<Transition>,
+    mut gate: ResMut<LevelSwitchGate>,
+    mut wants_to_move: ResMut<PlayerWantsToMove>,
+    mut movement_input: ResMut<MovementInput>,
+    mut wants_to_interact: ResMut<PlayerWantsToInteract>,
+) {
+    if switch_requested.iter().last().is_none() || transition.phase != TransitionPhase::Idle {
+        return;
+    }
+    gate.hold();
+    *transition = Transition {
+        phase: TransitionPhase::Covering,
+        timer: Timer::from_seconds(settings.duration, TimerMode::Once),
+        direction: facing.facing,
+    };
+    *wants_to_move = PlayerWantsToMove(false);
+    *movement_input = MovementInput::default();
+    *wants_to_interact = PlayerWantsToInteract(false);
+}
+
+pub (crate) fn advance_transition(
+    mut player_entered: EventReader<PlayerEnteredLevel>,
+    settings: Res<TransitionSettings>,
+    time: Res<Time>,
+    mut transition: ResMut<Transition>,
+    mut gate: ResMut<LevelSwitchGate>,
+) {
+    // The new level is in place once the player has been moved to their
+    // entrance.
+    let level_ready = player_entered.iter().any(|entered| !entered.scrolled);
+    match transition.phase {
+        TransitionPhase::Idle => {},
+        TransitionPhase::Covering => {
+            if transition.timer.tick(time.delta()).finished() {
+                transition.phase = TransitionPhase::Loading;
+                transition.timer = Timer::from_seconds(settings.loading_timeout, TimerMode::Once);
+                gate.release();
+            }
+        },
+        TransitionPhase::Loading => {
+            let timed_out = transition.timer.tick(time.delta()).finished();
+            if timed_out && !level_ready {
+                error!(
+                    "The player did not enter the new level within {}s, uncovering the screen anyway",
+                    settings.loading_timeout
+                );
+            }
+            if level_ready || timed_out {
+                transition.phase = TransitionPhase::Revealing;
+                transition.timer = Timer::from_seconds(settings.duration, TimerMode::Once);
+            }
+        },
+        TransitionPhase::Revealing => {
+            if transition.timer.tick(time.delta()).finished() {
+                transition.phase = TransitionPhase::Idle;
+            }
+        },
+    }
+}
+
+/// Position and size of `panel` in percent of the screen, as left, top, width
+/// and height, with its alpha.
+fn panel_layout(panel: CoverPanel, settings: &TransitionSettings, transition: &Transition) -> (Vec4, f32) {
+    let coverage = transition.coverage();
+    let hidden = (Vec4::ZERO, 0.);
+    match settings.style {
+        TransitionStyle::Fade => match panel {
+            CoverPanel::Top => (Vec4::new(0., 0., 100., 100.), coverage),
+            _ => hidden,
+        },
+        TransitionStyle::Slide => {
+            if panel != CoverPanel::Top || transition.phase == TransitionPhase::Idle {
+                return hidden;
+            }
+            // Screen space has y pointing down.
+            let offset = transition.direction.offset().as_vec2() * Vec2::new(1., -1.);
+            // Comes in from the side the player walks towards, and leaves on
+            // the side they came from.
+            let side = if transition.phase == TransitionPhase::Revealing { -1. } else { 1. };
+            let position = offset * side * (1. - coverage) * 100.;
+            (Vec4::new(position.x, position.y, 100., 100.), 1.)
+        },
+        TransitionStyle::Iris => {
+            let border = coverage * 50.;
+            let layout = match panel {
+                CoverPanel::Top => Vec4::new(0., 0., 100., border),
+                CoverPanel::Bottom => Vec4::new(0., 100. - border, 100., border),
+                CoverPanel::Left => Vec4::new(0., 0., border, 100.),
+                CoverPanel::Right => Vec4::new(100. - border, 0., border, 100.),
+            };
+            (layout, 1.)
+        },
+    }
+}
+
+pub (crate) fn draw_cover(
+    settings: Res<TransitionSettings>,
+    transition: Res<Transition>,
+    mut panels: Query<(&CoverPanel, &mut Style, &mut BackgroundColor)>,
+) {
+    if !transition.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (panel, mut style, mut color) in panels.iter_mut() {
+        let (layout, alpha) = panel_layout(*panel, &settings, &transition);
+        style.left = Val::Percent(layout.x);
+        style.top = Val::Percent(layout.y);
+        style.width = Val::Percent(layout.z);
+        style.height = Val::Percent(layout.w);
+        color.0.set_a(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+    use crate::Direction;
+
+    const FRAME_TIME: Duration = Duration::from_millis(100);
+
+    /// An app running only the transition state machine, with one second to
+    /// cover or uncover the screen and a two second loading timeout.
+    fn transition_app() -> App {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .insert_resource(TransitionSettings {
+                style: TransitionStyle::Fade,
+                duration: 1.,
+                loading_timeout: 2.,
+            })
+            .init_resource::<Transition>()
+            .init_resource::<LevelSwitchGate>()
+            .init_resource::<PlayerFacing>()
+            .init_resource::<PlayerWantsToMove>()
+            .init_resource::<MovementInput>()
+            .init_resource::<PlayerWantsToInteract>()
+            .add_event::<LevelSwitchRequested>()
+            .add_event::<PlayerEnteredLevel>()
+            .add_systems(Update, (start_transition, advance_transition).chain());
+        // The first update only starts the clock.
+        app.update();
+        app
+    }
+
+    fn request_switch(app: &mut App) {
+        app.world.send_event(LevelSwitchRequested {
+            to: "Crossroads".to_string(),
+            entrance: "West".to_string(),
+        });
+    }
+
+    fn step_seconds(app: &mut App, seconds: f32) {
+        let frames = (seconds / FRAME_TIME.as_secs_f32()).round() as usize;
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    fn phase(app: &App) -> TransitionPhase {
+        app.world.resource::<Transition>().phase
+    }
+
+    fn gate_held(app: &App) -> bool {
+        app.world.resource::<LevelSwitchGate>().is_held()
+    }
+
+    #[test]
+    fn switch_is_held_until_covered_and_revealed_once_the_player_enters() {
+        let mut app = transition_app();
+        request_switch(&mut app);
+        app.update();
+        assert_eq!(phase(&app), TransitionPhase::Covering);
+        assert!(gate_held(&app));
+
+        step_seconds(&mut app, 1.);
+        assert_eq!(phase(&app), TransitionPhase::Loading);
+        assert!(!gate_held(&app));
+
+        // Walking into an already spawned neighbour doesn't count.
+        app.world.send_event(PlayerEnteredLevel {
+            level_iid: "level".to_string(),
+            entrance: None,
+            scrolled: true,
+        });
+        app.update();
+        assert_eq!(phase(&app), TransitionPhase::Loading);
+
+        app.world.send_event(PlayerEnteredLevel {
+            level_iid: "level".to_string(),
+            entrance: Some("West".to_string()),
+            scrolled: false,
+        });
+        app.update();
+        assert_eq!(phase(&app), TransitionPhase::Revealing);
+
+        step_seconds(&mut app, 1.);
+        assert_eq!(phase(&app), TransitionPhase::Idle);
+    }
+
+    #[test]
+    fn loading_times_out_and_reveals_the_screen() {
+        let mut app = transition_app();
+        request_switch(&mut app);
+        app.update();
+        step_seconds(&mut app, 1.);
+        assert_eq!(phase(&app), TransitionPhase::Loading);
+
+        step_seconds(&mut app, 1.5);
+        assert_eq!(phase(&app), TransitionPhase::Loading);
+        step_seconds(&mut app, 0.5);
+        assert_eq!(phase(&app), TransitionPhase::Revealing);
+        step_seconds(&mut app, 1.);
+        assert_eq!(phase(&app), TransitionPhase::Idle);
+    }
+
+    #[test]
+    fn slide_enters_ahead_of_the_player_and_leaves_behind() {
+        let settings = TransitionSettings {
+            style: TransitionStyle::Slide,
+            ..default()
+        };
+        let mut transition = Transition {
+            phase: TransitionPhase::Covering,
+            timer: Timer::from_seconds(1., TimerMode::Once),
+            direction: Direction::East,
+        };
+        transition.timer.tick(std::time::Duration::from_secs_f32(0.25));
+        let (layout, _) = panel_layout(CoverPanel::Top, &settings, &transition);
+        assert_eq!(layout.x, 75.);
+
+        transition.phase = TransitionPhase::Revealing;
+        let (layout, _) = panel_layout(CoverPanel::Top, &settings, &transition);
+        assert_eq!(layout.x, -25.);
+    }
+}
@@ -1,5 +1,28 @@
 use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+
+use crate::constants::GRID_SIZE;
 
 pub trait MainState<T: States> {
     fn main() -> T;
-}
\ No newline at end of file
+}
+
+/// The grid cell `translation` is in. Unlike
+/// `bevy_ecs_ldtk::utils::translation_to_grid_coords`, which truncates towards
+/// zero, this rounds down, so that cells left of or below the origin of a
+/// seamless world aren't merged with the ones next to it.
+pub fn translation_to_grid_coords(translation: Vec2) -> GridCoords {
+    GridCoords::from((translation / GRID_SIZE as f32).floor().as_ivec2())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_translations_round_down() {
+        assert_eq!(translation_to_grid_coords(Vec2::new(16., 48.)), GridCoords::new(0, 1));
+        assert_eq!(translation_to_grid_coords(Vec2::new(-16., -48.)), GridCoords::new(-1, -2));
+        assert_eq!(translation_to_grid_coords(Vec2::new(-32., 0.)), GridCoords::new(-1, 0));
+    }
+}